@@ -1,4 +1,4 @@
-use crate::db::{ConversationEntry, Database, Link, Memory, Task};
+use crate::db::{ConversationEntry, Database, Link, Memory, Task, TaskComment, TaskEvent, TimeEntry};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -25,6 +25,8 @@ pub struct ArchiveFilters {
     pub project: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub expired: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -39,6 +41,12 @@ pub struct ArchiveCounts {
     #[serde(skip_serializing_if = "is_zero")]
     pub task_deps: usize,
     #[serde(skip_serializing_if = "is_zero")]
+    pub task_events: usize,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub task_comments: usize,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub time_entries: usize,
+    #[serde(skip_serializing_if = "is_zero")]
     pub links: usize,
 }
 
@@ -58,9 +66,16 @@ pub struct ArchiveData {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub task_deps: Vec<(i64, i64)>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub task_events: Vec<TaskEvent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub task_comments: Vec<TaskComment>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub time_entries: Vec<TimeEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub links: Vec<Link>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run_archive_create(
     db: &Database,
     db_path: &str,
@@ -69,10 +84,14 @@ pub fn run_archive_create(
     older_than_days: Option<i64>,
     project: Option<&str>,
     category: Option<&str>,
+    expired: bool,
     keep: bool,
     force: bool,
     limit: Option<usize>,
 ) -> Result<(), String> {
+    if expired && entity_type != "memories" {
+        return Err("--expired only applies to memories. Use --type memories.".to_string());
+    }
     if output.exists() && !force {
         return Err(format!(
             "Output file already exists: {}. Use --force to overwrite.",
@@ -90,7 +109,7 @@ pub fn run_archive_create(
 
     // Collect entities
     if archive_memories {
-        data.memories = db.query_memories_for_archive(category, older_than_days, limit)
+        data.memories = db.query_memories_for_archive(category, older_than_days, expired, limit)
             .map_err(|e| format!("Failed to query memories: {}", e))?;
         if !data.memories.is_empty() {
             entity_types.push("memories".to_string());
@@ -149,6 +168,18 @@ pub fn run_archive_create(
             data.task_deps = db.get_task_deps_for_task_ids(&all_task_ids)
                 .map_err(|e| format!("Failed to query task deps: {}", e))?;
 
+            // Cascade: collect task history
+            data.task_events = db.get_task_events_for_task_ids(&all_task_ids)
+                .map_err(|e| format!("Failed to query task events: {}", e))?;
+
+            // Cascade: collect task comments
+            data.task_comments = db.get_task_comments_for_task_ids(&all_task_ids)
+                .map_err(|e| format!("Failed to query task comments: {}", e))?;
+
+            // Cascade: collect tracked time
+            data.time_entries = db.get_time_entries_for_task_ids(&all_task_ids)
+                .map_err(|e| format!("Failed to query time entries: {}", e))?;
+
             // Cascade: collect links for these tasks
             let links = db.get_links_for_entity_ids("task", &all_task_ids)
                 .map_err(|e| format!("Failed to query links for tasks: {}", e))?;
@@ -188,12 +219,16 @@ pub fn run_archive_create(
             older_than_days,
             project: project.map(|s| s.to_string()),
             category: category.map(|s| s.to_string()),
+            expired,
         },
         counts: ArchiveCounts {
             memories: data.memories.len(),
             conversations: data.conversations.len(),
             tasks: data.tasks.len(),
             task_deps: data.task_deps.len(),
+            task_events: data.task_events.len(),
+            task_comments: data.task_comments.len(),
+            time_entries: data.time_entries.len(),
             links: data.links.len(),
         },
         data,
@@ -217,7 +252,7 @@ pub fn run_archive_create(
 
     // Delete from database (unless --keep)
     if !keep {
-        // Deletion order: links → task_deps → tasks → conversations → memories
+        // Deletion order: links → task_deps → task_events → task_comments → time_entries → tasks → conversations → memories
         let link_ids: Vec<i64> = envelope.data.links.iter().map(|l| l.id).collect();
         if !link_ids.is_empty() {
             db.delete_links_by_ids(&link_ids)
@@ -228,6 +263,12 @@ pub fn run_archive_create(
         if !task_ids.is_empty() {
             db.delete_task_deps_for_task_ids(&task_ids)
                 .map_err(|e| format!("Failed to delete archived task deps: {}", e))?;
+            db.delete_task_events_for_task_ids(&task_ids)
+                .map_err(|e| format!("Failed to delete archived task events: {}", e))?;
+            db.delete_task_comments_for_task_ids(&task_ids)
+                .map_err(|e| format!("Failed to delete archived task comments: {}", e))?;
+            db.delete_time_entries_for_task_ids(&task_ids)
+                .map_err(|e| format!("Failed to delete archived time entries: {}", e))?;
             db.delete_tasks_by_ids(&task_ids)
                 .map_err(|e| format!("Failed to delete archived tasks: {}", e))?;
         }
@@ -240,8 +281,13 @@ pub fn run_archive_create(
 
         let mem_ids: Vec<i64> = envelope.data.memories.iter().map(|m| m.id).collect();
         if !mem_ids.is_empty() {
-            db.delete_memories_by_ids(&mem_ids)
-                .map_err(|e| format!("Failed to delete archived memories: {}", e))?;
+            // Expired memories go for good; others keep their history
+            let deleted = if expired {
+                db.purge_memories_by_ids(&mem_ids)
+            } else {
+                db.delete_memories_by_ids(&mem_ids)
+            };
+            deleted.map_err(|e| format!("Failed to delete archived memories: {}", e))?;
         }
     }
 
@@ -271,6 +317,15 @@ pub fn run_archive_create(
     if envelope.counts.task_deps > 0 {
         println!("    task_deps: {}", envelope.counts.task_deps);
     }
+    if envelope.counts.task_events > 0 {
+        println!("    task_events: {}", envelope.counts.task_events);
+    }
+    if envelope.counts.task_comments > 0 {
+        println!("    task_comments: {}", envelope.counts.task_comments);
+    }
+    if envelope.counts.time_entries > 0 {
+        println!("    time_entries: {}", envelope.counts.time_entries);
+    }
     if envelope.counts.links > 0 {
         println!("    links: {}", envelope.counts.links);
     }
@@ -300,7 +355,7 @@ pub fn run_archive_restore(db: &Database, input: &Path) -> Result<(), String> {
     let mut restored = ArchiveCounts::default();
     let mut skipped = ArchiveCounts::default();
 
-    // Restore order: memories → conversations → tasks → task_deps → links
+    // Restore order: memories → conversations → tasks → task_deps → task_events → task_comments → time_entries → links
     for mem in &envelope.data.memories {
         match db.restore_memory(mem) {
             Ok(true) => restored.memories += 1,
@@ -337,6 +392,30 @@ pub fn run_archive_restore(db: &Database, input: &Path) -> Result<(), String> {
         }
     }
 
+    for event in &envelope.data.task_events {
+        match db.restore_task_event(event) {
+            Ok(true) => restored.task_events += 1,
+            Ok(false) => skipped.task_events += 1,
+            Err(e) => return Err(format!("Failed to restore task event {}: {}", event.id, e)),
+        }
+    }
+
+    for comment in &envelope.data.task_comments {
+        match db.restore_task_comment(comment) {
+            Ok(true) => restored.task_comments += 1,
+            Ok(false) => skipped.task_comments += 1,
+            Err(e) => return Err(format!("Failed to restore task comment {}: {}", comment.id, e)),
+        }
+    }
+
+    for entry in &envelope.data.time_entries {
+        match db.restore_time_entry(entry) {
+            Ok(true) => restored.time_entries += 1,
+            Ok(false) => skipped.time_entries += 1,
+            Err(e) => return Err(format!("Failed to restore time entry {}: {}", entry.id, e)),
+        }
+    }
+
     for link in &envelope.data.links {
         match db.restore_link(link) {
             Ok(true) => restored.links += 1,
@@ -347,8 +426,12 @@ pub fn run_archive_restore(db: &Database, input: &Path) -> Result<(), String> {
 
     println!("Archive restored from: {}", input.display());
     println!("  Restored / Skipped:");
-    let total_restored = restored.memories + restored.conversations + restored.tasks + restored.task_deps + restored.links;
-    let total_skipped = skipped.memories + skipped.conversations + skipped.tasks + skipped.task_deps + skipped.links;
+    let total_restored = restored.memories + restored.conversations + restored.tasks + restored.task_deps
+        + restored.task_events + restored.task_comments
+        + restored.time_entries + restored.links;
+    let total_skipped = skipped.memories + skipped.conversations + skipped.tasks + skipped.task_deps
+        + skipped.task_events + skipped.task_comments
+        + skipped.time_entries + skipped.links;
 
     if envelope.counts.memories > 0 {
         println!("    memories: {} restored, {} skipped", restored.memories, skipped.memories);
@@ -362,6 +445,15 @@ pub fn run_archive_restore(db: &Database, input: &Path) -> Result<(), String> {
     if envelope.counts.task_deps > 0 {
         println!("    task_deps: {} restored, {} skipped", restored.task_deps, skipped.task_deps);
     }
+    if envelope.counts.task_events > 0 {
+        println!("    task_events: {} restored, {} skipped", restored.task_events, skipped.task_events);
+    }
+    if envelope.counts.task_comments > 0 {
+        println!("    task_comments: {} restored, {} skipped", restored.task_comments, skipped.task_comments);
+    }
+    if envelope.counts.time_entries > 0 {
+        println!("    time_entries: {} restored, {} skipped", restored.time_entries, skipped.time_entries);
+    }
     if envelope.counts.links > 0 {
         println!("    links: {} restored, {} skipped", restored.links, skipped.links);
     }
@@ -0,0 +1,302 @@
+//! SessionStart briefing: open tasks, recent session summaries and pinned
+//! memories for the project, rendered through a template within a token
+//! budget.
+
+use std::path::Path;
+
+use crate::config::BriefingSettings;
+use crate::db::{ConversationEntry, Database, Memory, Task};
+use crate::recall::estimate_tokens;
+use crate::toolcalls::truncate_chars;
+
+/// The built-in template. Placeholders are listed in `PLACEHOLDERS`.
+pub const DEFAULT_TEMPLATE: &str = "[leafhill-memory] session_id={session_id} project={project}. \
+After EVERY exchange, call log_conversation with this session_id, role=\"summary\", \
+entry_type=\"summary\", and a concise summary of what was discussed/done.
+
+{pinned}{tasks}{summaries}";
+
+pub const PLACEHOLDERS: &[&str] = &["session_id", "project", "pinned", "tasks", "summaries"];
+
+/// Longest line kept per task, summary or memory, in chars.
+const LINE_MAX_CHARS: usize = 300;
+
+/// Settings for the briefing: the `[briefing]` section of the config file
+/// (see `config`), then these environment variables over it:
+///
+/// - `CLAUDE_MEMORY_BRIEFING_TASKS` — most open tasks listed (default 10)
+/// - `CLAUDE_MEMORY_BRIEFING_SUMMARIES` — most recent summaries listed (default 3)
+/// - `CLAUDE_MEMORY_BRIEFING_PINNED` — most pinned memories listed (default 10)
+/// - `CLAUDE_MEMORY_BRIEFING_TOKENS` — token budget for the briefing (default 1500)
+/// - `CLAUDE_MEMORY_BRIEFING_TEMPLATE` — path to a template file
+#[derive(Debug, Clone, PartialEq)]
+pub struct BriefingConfig {
+    pub tasks: usize,
+    pub summaries: usize,
+    pub pinned: usize,
+    pub token_budget: usize,
+    pub template: String,
+}
+
+impl Default for BriefingConfig {
+    fn default() -> Self {
+        BriefingConfig {
+            tasks: 10,
+            summaries: 3,
+            pinned: 10,
+            token_budget: 1500,
+            template: DEFAULT_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl BriefingConfig {
+    pub fn load(settings: &BriefingSettings) -> Result<Self, String> {
+        Self::from_vars(Self::from_settings(settings)?, |name| std::env::var(name).ok())
+    }
+
+    fn from_settings(settings: &BriefingSettings) -> Result<Self, String> {
+        let defaults = BriefingConfig::default();
+        let template = match (&settings.template, &settings.template_file) {
+            (Some(t), _) => t.clone(),
+            (None, Some(path)) => load_template(path)?,
+            (None, None) => defaults.template,
+        };
+        Ok(BriefingConfig {
+            tasks: settings.tasks.unwrap_or(defaults.tasks),
+            summaries: settings.summaries.unwrap_or(defaults.summaries),
+            pinned: settings.pinned.unwrap_or(defaults.pinned),
+            token_budget: settings.tokens.unwrap_or(defaults.token_budget),
+            template,
+        })
+    }
+
+    fn from_vars(mut config: BriefingConfig, var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let count = |name: &str, default: usize| match var(name) {
+            Some(v) => v.trim().parse()
+                .map_err(|_| format!("Invalid {} '{}'. Use a whole number", name, v)),
+            None => Ok(default),
+        };
+        config.tasks = count("CLAUDE_MEMORY_BRIEFING_TASKS", config.tasks)?;
+        config.summaries = count("CLAUDE_MEMORY_BRIEFING_SUMMARIES", config.summaries)?;
+        config.pinned = count("CLAUDE_MEMORY_BRIEFING_PINNED", config.pinned)?;
+        config.token_budget = count("CLAUDE_MEMORY_BRIEFING_TOKENS", config.token_budget)?;
+        if let Some(path) = var("CLAUDE_MEMORY_BRIEFING_TEMPLATE").filter(|p| !p.trim().is_empty()) {
+            config.template = load_template(Path::new(path.trim()))?;
+        }
+        Ok(config)
+    }
+}
+
+/// Read a template file, rejecting placeholders the briefing does not fill.
+pub fn load_template(path: &Path) -> Result<String, String> {
+    let template = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read briefing template {}: {}", path.display(), e))?;
+    validate_template(&template)?;
+    Ok(template)
+}
+
+pub(crate) fn validate_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('}') else { break };
+        let name = &rest[..end];
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "Unknown briefing placeholder '{{{}}}'. Must be one of: {}",
+                name,
+                PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// One titled list in the briefing, e.g. the open tasks.
+struct Section {
+    placeholder: &'static str,
+    title: &'static str,
+    lines: Vec<String>,
+}
+
+fn task_line(task: &Task) -> String {
+    let mut line = format!("- #{} [{}", task.id, task.status);
+    if let Some(p) = &task.priority {
+        line.push_str(&format!(", {}", p));
+    }
+    line.push_str(&format!("] {}", task.subject));
+    if let Some(due) = &task.due_date {
+        line.push_str(&format!(" (due {})", due));
+    }
+    line
+}
+
+fn summary_line(entry: &ConversationEntry) -> String {
+    let when = entry.message_timestamp.as_deref().unwrap_or(&entry.created_at);
+    format!("- ({}) {}", when.get(..10).unwrap_or(when), entry.content.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn memory_line(memory: &Memory) -> String {
+    format!("- {}/{}: {}", memory.category, memory.key, memory.value.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Replace each placeholder in one pass over `template`, so text that is
+/// filled in is never scanned for placeholders itself.
+fn substitute(template: &str, session_id: &str, project: &str, sections: &[(&str, String)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name = after.find('}').map(|end| &after[..end]);
+        let value = match name {
+            Some("session_id") => Some(session_id),
+            Some("project") => Some(project),
+            Some(name) => sections.iter().find(|(p, _)| *p == name).map(|(_, body)| body.as_str()),
+            None => None,
+        };
+        match (name, value) {
+            (Some(name), Some(value)) => {
+                text.push_str(value);
+                rest = &after[name.len() + 1..];
+            }
+            _ => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text.trim_end().to_string()
+}
+
+/// `template` with every section left empty.
+pub fn without_sections(template: &str, session_id: &str, project: &str) -> String {
+    let empty: Vec<(&str, String)> = ["pinned", "tasks", "summaries"].iter().map(|p| (*p, String::new())).collect();
+    substitute(template, session_id, project, &empty)
+}
+
+/// Fill `template`, adding section lines in order (pinned memories, then
+/// tasks, then summaries) while they fit in `token_budget`. The template's
+/// own text is always kept, even when it alone is over budget.
+fn render(template: &str, session_id: &str, project: &str, sections: Vec<Section>, token_budget: usize) -> String {
+    let mut used = estimate_tokens(&without_sections(template, session_id, project));
+    let mut bodies = Vec::new();
+    for section in sections {
+        let mut body = String::new();
+        if template.contains(&format!("{{{}}}", section.placeholder)) && !section.lines.is_empty() {
+            let heading = format!("{}:\n", section.title);
+            let mut omitted = 0;
+            for line in &section.lines {
+                let line = format!("{}\n", truncate_chars(line, LINE_MAX_CHARS));
+                let cost = estimate_tokens(&line) + if body.is_empty() { estimate_tokens(&heading) } else { 0 };
+                if used + cost > token_budget {
+                    omitted += 1;
+                    continue;
+                }
+                if body.is_empty() {
+                    body.push_str(&heading);
+                }
+                body.push_str(&line);
+                used += cost;
+            }
+            if !body.is_empty() {
+                let more = format!("- ({} more not shown)\n", omitted);
+                if omitted > 0 && used + estimate_tokens(&more) <= token_budget {
+                    used += estimate_tokens(&more);
+                    body.push_str(&more);
+                }
+                body.push('\n');
+            }
+        }
+        bodies.push((section.placeholder, body));
+    }
+    substitute(template, session_id, project, &bodies)
+}
+
+/// The briefing for a session starting in `project`.
+pub fn build(db: &Database, session_id: &str, project: &str, config: &BriefingConfig) -> rusqlite::Result<String> {
+    let pinned = if config.pinned > 0 { db.pinned_memories(config.pinned)? } else { Vec::new() };
+    let tasks = if config.tasks > 0 { db.open_tasks(Some(project), config.tasks)? } else { Vec::new() };
+    let summaries = if config.summaries > 0 {
+        db.recent_summaries(Some(project), None, config.summaries)?
+    } else {
+        Vec::new()
+    };
+    let sections = vec![
+        Section { placeholder: "pinned", title: "Pinned memories", lines: pinned.iter().map(memory_line).collect() },
+        Section { placeholder: "tasks", title: "Open tasks", lines: tasks.iter().map(task_line).collect() },
+        Section {
+            placeholder: "summaries",
+            title: "Recent session summaries",
+            lines: summaries.iter().map(summary_line).collect(),
+        },
+    ];
+    Ok(render(&config.template, session_id, project, sections, config.token_budget))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_from_vars() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| pairs.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+        };
+        let base = || BriefingConfig::default();
+        assert_eq!(BriefingConfig::from_vars(base(), vars(&[])).unwrap(), BriefingConfig::default());
+        let settings = BriefingSettings { tasks: Some(4), summaries: Some(1), ..Default::default() };
+        let config = BriefingConfig::from_vars(BriefingConfig::from_settings(&settings).unwrap(), vars(&[
+            ("CLAUDE_MEMORY_BRIEFING_SUMMARIES", "5"),
+            ("CLAUDE_MEMORY_BRIEFING_TOKENS", "200"),
+        ])).unwrap();
+        assert_eq!((config.tasks, config.summaries, config.token_budget), (4, 5, 200));
+        assert!(BriefingConfig::from_vars(base(), vars(&[("CLAUDE_MEMORY_BRIEFING_TASKS", "all")])).is_err());
+        assert!(BriefingConfig::from_vars(base(), vars(&[("CLAUDE_MEMORY_BRIEFING_TEMPLATE", "/nonexistent/t.txt")])).is_err());
+        let inline = BriefingSettings { template: Some("{project}: {tasks}".to_string()), ..Default::default() };
+        assert_eq!(BriefingConfig::from_settings(&inline).unwrap().template, "{project}: {tasks}");
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template(DEFAULT_TEMPLATE).is_ok());
+        assert!(validate_template("{tasks} in {\"json\": 1}").is_ok());
+        assert!(validate_template("{project} {memories}").unwrap_err().contains("'{memories}'"));
+    }
+
+    #[test]
+    fn test_build_briefing() {
+        let db = Database::open_in_memory().unwrap();
+        db.store_memory("prefs", "style", "Prefer small commits", Some(&["pinned".to_string()]), None).unwrap();
+        db.store_memory("facts", "colour", "Favourite colour is green", Some(&["misc".to_string()]), None).unwrap();
+        let pending = db.create_task("repo", "Write docs", None, None, None, None, None, None, None, None, None, None).unwrap();
+        let active = db.create_task("repo", "Fix login", None, None, None, None, None, None, None, None, None, None).unwrap();
+        db.update_task(active.id, &serde_json::json!({"status": "in_progress"})).unwrap();
+        db.create_task("other", "Elsewhere", None, None, None, None, None, None, None, None, None, None).unwrap();
+        db.log_conversation("old", "summary", "Set up CI", Some("repo"), Some("summary"), None).unwrap();
+
+        let text = build(&db, "s1-repo", "repo", &BriefingConfig::default()).unwrap();
+        assert!(text.starts_with("[leafhill-memory] session_id=s1-repo project=repo."));
+        assert!(text.contains("Pinned memories:\n- prefs/style: Prefer small commits\n"));
+        assert!(!text.contains("colour"));
+        let fix = text.find(&format!("#{} [in_progress] Fix login", active.id)).unwrap();
+        assert!(fix < text.find(&format!("#{} [pending] Write docs", pending.id)).unwrap());
+        assert!(!text.contains("Elsewhere"));
+        assert!(text.ends_with("Set up CI"));
+
+        let template = "Tasks for {project}:\n{tasks}".to_string();
+        let tight = BriefingConfig { token_budget: 20, template, ..BriefingConfig::default() };
+        let text = build(&db, "s1-repo", "repo", &tight).unwrap();
+        assert!(text.contains("Fix login") && !text.contains("Write docs"), "{}", text);
+        assert!(!text.contains("Pinned"));
+    }
+
+    #[test]
+    fn test_substitute_does_not_expand_filled_in_text() {
+        let sections = [("pinned", "- notes/t: see {tasks} for {project}".to_string()), ("tasks", "- #1 Fix".to_string())];
+        let text = substitute("{session_id} {project}\n{pinned}\n{tasks} {json: {}", "{project}", "repo", &sections);
+        assert_eq!(text, "{project} repo\n- notes/t: see {tasks} for {project}\n- #1 Fix {json: {}");
+    }
+}
@@ -1,5 +1,14 @@
 use clap::{Parser, Subcommand};
-use crate::db::{Database, TaskStatus, TaskPriority, TaskType};
+use crate::briefing::{self, BriefingConfig};
+use crate::config::Config;
+use crate::db::{Database, EntryType, TaskStatus, TaskPriority, TaskType};
+use crate::embedding::SearchMode;
+use crate::projects;
+use crate::recall::{self, RecallConfig};
+use crate::timetrack::{format_duration, parse_duration, TimeGroup};
+use crate::toolcalls::{summarize_input, truncate_chars, ToolCallFilter};
+use crate::transcript;
+use crate::usage::{self, PriceTable, UsageFilter, UsageGroup};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -13,8 +22,12 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Start the MCP server (stdio transport)
-    Serve,
+    /// Start the MCP server (stdio transport, or Streamable HTTP with --http)
+    Serve {
+        /// Listen address for the Streamable HTTP transport (e.g. 127.0.0.1:8765)
+        #[arg(long)]
+        http: Option<String>,
+    },
 
     /// Store a memory (upserts if category+key exists)
     Store {
@@ -27,6 +40,12 @@ pub enum Commands {
         /// Comma-separated tags
         #[arg(long)]
         tags: Option<String>,
+        /// Expire after a duration (e.g. 12h, 7d, 2w)
+        #[arg(long, conflicts_with = "expires_at")]
+        ttl: Option<String>,
+        /// Expire at a UTC time (RFC 3339, "YYYY-MM-DD HH:MM:SS" or "YYYY-MM-DD")
+        #[arg(long)]
+        expires_at: Option<String>,
     },
 
     /// Full-text search across memories
@@ -39,6 +58,12 @@ pub enum Commands {
         /// Max results
         #[arg(long, short, default_value = "20")]
         limit: usize,
+        /// Search mode: fts, hybrid, semantic (hybrid/semantic need CLAUDE_MEMORY_EMBEDDER)
+        #[arg(long, default_value = "fts")]
+        mode: String,
+        /// Include memories whose expiry has passed
+        #[arg(long)]
+        include_expired: bool,
     },
 
     /// List memories
@@ -49,6 +74,9 @@ pub enum Commands {
         /// Max results
         #[arg(long, short, default_value = "50")]
         limit: usize,
+        /// Include memories whose expiry has passed
+        #[arg(long)]
+        include_expired: bool,
     },
 
     /// Delete a memory
@@ -59,6 +87,38 @@ pub enum Commands {
         key: String,
     },
 
+    /// Show prior revisions of a memory
+    History {
+        /// Category
+        category: String,
+        /// Key
+        key: String,
+    },
+
+    /// Remove expired memories, optionally archiving them first
+    Gc {
+        /// Archive expired memories to this JSON file before deleting them
+        #[arg(long)]
+        archive: Option<String>,
+        /// Overwrite an existing archive file
+        #[arg(long)]
+        force: bool,
+        /// List expired memories without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Restore a memory to a prior revision
+    Revert {
+        /// Category
+        category: String,
+        /// Key
+        key: String,
+        /// Revision number (see `history`)
+        #[arg(long)]
+        to: i64,
+    },
+
     /// Handle Claude Code hook events (reads JSON from stdin)
     HookHandler,
 
@@ -80,6 +140,23 @@ pub enum Commands {
         command: LogCommands,
     },
 
+    /// Project registry: names, aliases, renames and merges
+    Project {
+        #[command(subcommand)]
+        command: ProjectCommands,
+    },
+
+    /// Find secrets and personal data already stored (see [redaction] in the config)
+    ///
+    /// Checks memory keys, values and tags and their revisions, conversation
+    /// entries, tool call inputs and results, task subjects and descriptions,
+    /// task comments, and the old and new values in task history.
+    Scan {
+        /// Replace what is found instead of only reporting it
+        #[arg(long)]
+        scrub: bool,
+    },
+
     /// Create a backup of the database
     Backup {
         /// Output file path
@@ -103,6 +180,257 @@ pub enum Commands {
         #[arg(long)]
         tables: Option<String>,
     },
+
+    /// Embedding index operations (semantic search)
+    Embed {
+        #[command(subcommand)]
+        command: EmbedCommands,
+    },
+
+    /// Import data from outside sources
+    Import {
+        #[command(subcommand)]
+        command: ImportCommands,
+    },
+
+    /// Preview what the UserPromptSubmit hook would recall for a prompt
+    Recall {
+        /// The prompt text
+        prompt: String,
+        /// Project the prompt was made in (limits tasks and summaries)
+        #[arg(long, short)]
+        project: Option<String>,
+        /// Most hits (default: $CLAUDE_MEMORY_RECALL_LIMIT or 5)
+        #[arg(long, short)]
+        limit: Option<usize>,
+        /// Token budget (default: $CLAUDE_MEMORY_RECALL_TOKENS or 600)
+        #[arg(long)]
+        tokens: Option<usize>,
+        /// Minimum relevance from 0 to 1 (default: $CLAUDE_MEMORY_RECALL_MIN_SCORE or 0.4)
+        #[arg(long)]
+        min_score: Option<f64>,
+    },
+
+    /// Preview the briefing the SessionStart hook injects
+    Briefing {
+        /// Project the session starts in
+        #[arg(long, short)]
+        project: String,
+        /// Session ID shown in the briefing
+        #[arg(long, default_value = "preview")]
+        session_id: String,
+        /// Token budget (default: $CLAUDE_MEMORY_BRIEFING_TOKENS or 1500)
+        #[arg(long)]
+        tokens: Option<usize>,
+        /// Template file (default: $CLAUDE_MEMORY_BRIEFING_TEMPLATE or the built-in one)
+        #[arg(long)]
+        template: Option<PathBuf>,
+    },
+
+    /// Tool calls captured from transcripts
+    ToolCalls {
+        #[command(subcommand)]
+        command: ToolCallCommands,
+    },
+
+    /// Usage statistics
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommands,
+    },
+
+    /// Schema migration operations
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommands,
+    },
+
+    /// Hook configuration (leafhill-memory.toml)
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum EmbedCommands {
+    /// Show the configured embedder and stored vector counts
+    Status,
+
+    /// Recompute vectors for existing rows with the configured embedder
+    Reindex {
+        /// Entity type to reindex: memories, conversations, tasks, all
+        #[arg(long, name = "type", default_value = "all")]
+        entity_type: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ImportCommands {
+    /// Import Claude Code JSONL transcripts (already-stored messages are skipped)
+    Transcript {
+        /// Transcript file or directory to search for *.jsonl (default: ~/.claude/projects)
+        path: Option<PathBuf>,
+        /// Read every transcript from the start, ignoring where earlier imports stopped
+        #[arg(long)]
+        rescan: bool,
+        /// Count messages without storing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProjectCommands {
+    /// List registered projects with their aliases, remotes and roots
+    List,
+
+    /// Show which project a directory belongs to, registering it if new
+    Resolve {
+        /// Directory (default: the current directory)
+        path: Option<PathBuf>,
+    },
+
+    /// Make another name resolve to a project
+    Alias {
+        /// Project name
+        project: String,
+        /// Alias to add
+        alias: String,
+    },
+
+    /// Remove an alias
+    Unalias {
+        /// Alias to remove
+        alias: String,
+    },
+
+    /// Rename a project, rewriting its tasks, conversations and tool calls
+    Rename {
+        /// Current name
+        old: String,
+        /// New name
+        new: String,
+    },
+
+    /// Fold one project into another; the first becomes an alias of the second
+    Merge {
+        /// Project to fold away
+        from: String,
+        /// Project to keep
+        into: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ToolCallCommands {
+    /// List tool calls, most recent first
+    List {
+        /// Filter by session ID
+        #[arg(long, short)]
+        session: Option<String>,
+        /// Filter by project
+        #[arg(long, short)]
+        project: Option<String>,
+        /// Filter by tool name (e.g. Bash, Edit)
+        #[arg(long, short)]
+        tool: Option<String>,
+        /// Only calls that returned an error
+        #[arg(long)]
+        errors: bool,
+        /// Only calls since an age (e.g. 7d) or date (e.g. 2026-01-01)
+        #[arg(long)]
+        since: Option<String>,
+        /// Max results
+        #[arg(long, short, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Show a tool call's full input and result
+    Get {
+        /// Tool call ID
+        id: i64,
+    },
+
+    /// Files written by Edit, MultiEdit, Write and NotebookEdit calls
+    Files {
+        /// Filter by session ID
+        #[arg(long, short)]
+        session: Option<String>,
+        /// Filter by project
+        #[arg(long, short)]
+        project: Option<String>,
+    },
+
+    /// Tools ranked by how often they failed
+    Failures {
+        /// Filter by project
+        #[arg(long, short)]
+        project: Option<String>,
+        /// Only calls since an age (e.g. 7d) or date (e.g. 2026-01-01)
+        #[arg(long)]
+        since: Option<String>,
+        /// Max tools
+        #[arg(long, short, default_value = "20")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum StatsCommands {
+    /// Token usage and estimated cost from conversation metadata
+    Tokens {
+        /// Comma-separated grouping: project, session, model, day, week
+        #[arg(long, default_value = "project")]
+        by: String,
+        /// Only count this project
+        #[arg(long, short)]
+        project: Option<String>,
+        /// Only count this session
+        #[arg(long, short)]
+        session: Option<String>,
+        /// Only count this model
+        #[arg(long, short)]
+        model: Option<String>,
+        /// Only count messages since an age (e.g. 7d) or date (e.g. 2026-01-01)
+        #[arg(long)]
+        since: Option<String>,
+        /// JSON price table (USD per million tokens by model prefix); default: $CLAUDE_MEMORY_PRICES
+        #[arg(long)]
+        prices: Option<PathBuf>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MigrateCommands {
+    /// Show the schema version and applied/pending migrations
+    Status,
+
+    /// Apply pending migrations
+    Up {
+        /// Stop after this version (default: latest)
+        #[arg(long)]
+        to: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Show the effective configuration and the files it came from
+    Show {
+        /// Project directory (default: the current directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+
+    /// Check a config file, or the files `show` would read
+    Validate {
+        /// File to check
+        file: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -120,6 +448,9 @@ pub enum LogCommands {
         /// Max results
         #[arg(long, short, default_value = "20")]
         limit: usize,
+        /// Search mode: fts, hybrid, semantic
+        #[arg(long, default_value = "fts")]
+        mode: String,
     },
 
     /// List conversation entries
@@ -144,6 +475,15 @@ pub enum LogCommands {
         limit: usize,
     },
 
+    /// Show every entry of a session in order: prompts, replies, tool activity, subagents and lifecycle events
+    Timeline {
+        /// Session ID
+        session_id: String,
+        /// Only show entries of these comma-separated types (e.g. raw_user,tool_use)
+        #[arg(long, name = "type")]
+        entry_type: Option<String>,
+    },
+
     /// Prune old conversation entries
     Prune {
         /// Delete entries older than N days
@@ -153,6 +493,13 @@ pub enum LogCommands {
         #[arg(long, name = "type")]
         entry_type: Option<String>,
     },
+
+    /// Remove duplicate transcript messages left by repeated PreCompact storage
+    Dedupe {
+        /// Count duplicates without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -184,6 +531,9 @@ pub enum TaskCommands {
         /// Parent task ID (for subtasks)
         #[arg(long)]
         parent: Option<i64>,
+        /// Repeat on completion: daily, weekly, monthly, yearly, or an RRULE (e.g. FREQ=WEEKLY;BYDAY=MO)
+        #[arg(long)]
+        recurrence: Option<String>,
     },
 
     /// List tasks with filters
@@ -203,6 +553,9 @@ pub enum TaskCommands {
         /// Filter by priority
         #[arg(long)]
         priority: Option<String>,
+        /// Only actionable tasks (pending or in progress, all blockers completed)
+        #[arg(long)]
+        ready: bool,
         /// Max results
         #[arg(long, short, default_value = "50")]
         limit: usize,
@@ -239,6 +592,15 @@ pub enum TaskCommands {
         /// New due date
         #[arg(long)]
         due: Option<String>,
+        /// New recurrence rule (daily, weekly, monthly, yearly, or an RRULE)
+        #[arg(long, conflicts_with = "no_recurrence")]
+        recurrence: Option<String>,
+        /// Stop the task from recurring
+        #[arg(long)]
+        no_recurrence: bool,
+        /// Who is making the change (recorded in the task history)
+        #[arg(long)]
+        actor: Option<String>,
     },
 
     /// Search tasks by text
@@ -254,6 +616,9 @@ pub enum TaskCommands {
         /// Max results
         #[arg(long, short, default_value = "20")]
         limit: usize,
+        /// Search mode: fts, hybrid, semantic
+        #[arg(long, default_value = "fts")]
+        mode: String,
     },
 
     /// Delete a task (soft-delete)
@@ -268,6 +633,12 @@ pub enum TaskCommands {
         id: i64,
     },
 
+    /// Show the change history of a task
+    History {
+        /// Task ID
+        id: i64,
+    },
+
     /// Add a dependency between tasks
     AddDep {
         /// Blocker task ID
@@ -283,6 +654,126 @@ pub enum TaskCommands {
         /// Blocked task ID
         blocked: i64,
     },
+
+    /// Show dependency order, ready tasks and the longest blocking chain for a project
+    CriticalPath {
+        /// Project name
+        #[arg(long, short)]
+        project: String,
+    },
+
+    /// Add, list or search task comments
+    Comment {
+        #[command(subcommand)]
+        command: CommentCommands,
+    },
+
+    /// Track time spent on tasks
+    Time {
+        #[command(subcommand)]
+        command: TimeCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TimeCommands {
+    /// Start a timer on a task
+    Start {
+        /// Task ID
+        task_id: i64,
+        /// Session the work belongs to
+        #[arg(long)]
+        session: Option<String>,
+        /// Who is working
+        #[arg(long, short)]
+        actor: Option<String>,
+        /// What the time is for
+        #[arg(long, short)]
+        note: Option<String>,
+    },
+
+    /// Stop running timers on a task (all of them unless --session is given)
+    Stop {
+        /// Task ID
+        task_id: i64,
+        /// Only stop this session's timer
+        #[arg(long)]
+        session: Option<String>,
+    },
+
+    /// Record time already spent on a task
+    Log {
+        /// Task ID
+        task_id: i64,
+        /// Time spent, e.g. 45m or 1h30m (bare numbers are seconds)
+        duration: String,
+        /// When the work started (RFC 3339 or YYYY-MM-DD HH:MM:SS UTC); default: duration before now
+        #[arg(long)]
+        started_at: Option<String>,
+        /// Session the work belongs to
+        #[arg(long)]
+        session: Option<String>,
+        /// Who did the work
+        #[arg(long, short)]
+        actor: Option<String>,
+        /// What the time was for
+        #[arg(long, short)]
+        note: Option<String>,
+    },
+
+    /// List time entries for a task
+    List {
+        /// Task ID
+        task_id: i64,
+    },
+
+    /// Total tracked time by project, assignee, task_type or source (claude/human)
+    Report {
+        /// Grouping: project, assignee, task_type, source
+        #[arg(long, default_value = "project")]
+        by: String,
+        /// Only count tasks in this project
+        #[arg(long, short)]
+        project: Option<String>,
+        /// Only count entries started at or after this date/time
+        #[arg(long)]
+        since: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CommentCommands {
+    /// Comment on a task
+    Add {
+        /// Task ID
+        task_id: i64,
+        /// Comment text
+        body: String,
+        /// Who is commenting
+        #[arg(long, short)]
+        author: Option<String>,
+        /// Comment ID this replies to
+        #[arg(long)]
+        reply_to: Option<i64>,
+    },
+
+    /// Show a task's comment threads
+    List {
+        /// Task ID
+        task_id: i64,
+    },
+
+    /// Full-text search across task comments
+    Search {
+        /// Search query
+        query: String,
+        /// Only search comments on this task
+        #[arg(long)]
+        task: Option<i64>,
+        /// Max results
+        #[arg(long, short, default_value = "20")]
+        limit: usize,
+    },
 }
 
 #[derive(Subcommand)]
@@ -335,6 +826,9 @@ pub enum ArchiveCommands {
         /// Filter by category (memories only)
         #[arg(long, short)]
         category: Option<String>,
+        /// Only archive memories whose expiry has passed (memories only)
+        #[arg(long)]
+        expired: bool,
         /// Maximum number of entities per type to archive (default: unlimited)
         #[arg(long)]
         limit: Option<usize>,
@@ -354,29 +848,42 @@ pub enum ArchiveCommands {
 }
 
 pub fn run_cli(command: Commands, db_path: &PathBuf) {
-    let db = match Database::open(db_path) {
+    let mut db = match Database::open(db_path) {
         Ok(db) => db,
         Err(e) => {
             eprintln!("Failed to open database at {:?}: {}", db_path, e);
             std::process::exit(1);
         }
     };
+    db.set_redactor(Config::load_for_current_dir().redactor());
 
     match command {
-        Commands::Serve => unreachable!("serve handled in main"),
+        Commands::Serve { .. } => unreachable!("serve handled in main"),
         Commands::HookHandler => unreachable!("hook-handler handled in main"),
+        Commands::Migrate { .. } => unreachable!("migrate handled in main"),
+        Commands::Config { .. } => unreachable!("config handled in main"),
 
-        Commands::Store { category, key, value, tags } => {
+        Commands::Store { category, key, value, tags, ttl, expires_at } => {
             let tag_vec: Option<Vec<String>> = tags.map(|t| {
                 t.split(',').map(|s| s.trim().to_string()).collect()
             });
-            match db.store_memory(&category, &key, &value, tag_vec.as_deref()) {
+            let expires_at = match crate::db::resolve_expiry(expires_at.as_deref(), ttl.as_deref()) {
+                Ok(e) => e,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            match db.store_memory(&category, &key, &value, tag_vec.as_deref(), expires_at.as_deref()) {
                 Ok(mem) => {
                     println!("Stored: [{}:{}]", mem.category, mem.key);
                     println!("  Value: {}", mem.value);
                     if let Some(tags) = &mem.tags {
                         println!("  Tags: {}", tags.join(", "));
                     }
+                    if let Some(expires_at) = &mem.expires_at {
+                        println!("  Expires: {}", expires_at);
+                    }
                     println!("  Updated: {}", mem.updated_at);
                 }
                 Err(e) => {
@@ -386,8 +893,9 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
             }
         }
 
-        Commands::Search { query, category, limit } => {
-            match db.search_memories(&query, category.as_deref(), limit) {
+        Commands::Search { query, category, limit, mode, include_expired } => {
+            let mode = parse_search_mode(&mode);
+            match db.semantic_search_memories(&query, category.as_deref(), limit, mode, include_expired) {
                 Ok(memories) => {
                     if memories.is_empty() {
                         println!("No memories found.");
@@ -405,8 +913,8 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
             }
         }
 
-        Commands::List { category, limit } => {
-            match db.list_memories(category.as_deref(), limit) {
+        Commands::List { category, limit, include_expired } => {
+            match db.list_memories(category.as_deref(), limit, include_expired) {
                 Ok(memories) => {
                     if memories.is_empty() {
                         println!("No memories found.");
@@ -438,8 +946,81 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
             }
         }
 
+        Commands::Gc { archive, force, dry_run } => {
+            if dry_run {
+                match db.expired_memories() {
+                    Ok(memories) => {
+                        if memories.is_empty() {
+                            println!("No expired memories.");
+                        } else {
+                            for mem in &memories {
+                                print_memory(mem);
+                            }
+                            println!("\n({} expired memories would be removed)", memories.len());
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Gc failed: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else if let Some(output) = archive {
+                let db_path_str = db_path.to_string_lossy().to_string();
+                if let Err(e) = crate::archive::run_archive_create(
+                    &db, &db_path_str, std::path::Path::new(&output), "memories",
+                    None, None, None, true, false, force, None,
+                ) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            } else {
+                match db.purge_expired_memories() {
+                    Ok(n) => println!("Removed {} expired memories.", n),
+                    Err(e) => {
+                        eprintln!("Gc failed: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+
+        Commands::History { category, key } => {
+            match db.memory_history(&category, &key) {
+                Ok(revisions) => {
+                    if revisions.is_empty() {
+                        println!("No revisions found for [{}:{}].", category, key);
+                    } else {
+                        for rev in &revisions {
+                            print_memory_revision(rev);
+                        }
+                        println!("\n({} revisions)", revisions.len());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("History failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Revert { category, key, to } => {
+            match db.revert_memory(&category, &key, to) {
+                Ok(mem) => {
+                    println!("Reverted [{}:{}] to revision {}", mem.category, mem.key, to);
+                    println!("  Value: {}", mem.value);
+                    if let Some(tags) = &mem.tags {
+                        println!("  Tags: {}", tags.join(", "));
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Revert failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
         Commands::Task { command: task_cmd } => match task_cmd {
-            TaskCommands::Create { project, subject, description, priority, task_type, assignee, owner, due, parent } => {
+            TaskCommands::Create { project, subject, description, priority, task_type, assignee, owner, due, parent, recurrence } => {
                 if let Some(ref p) = priority {
                     if let Err(e) = p.parse::<TaskPriority>() { eprintln!("{}", e); std::process::exit(1); }
                 }
@@ -448,15 +1029,15 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
                 }
                 match db.create_task(&project, &subject, description.as_deref(), priority.as_deref(),
                     task_type.as_deref(), parent, due.as_deref(), None, assignee.as_deref(),
-                    owner.as_deref(), None)
+                    owner.as_deref(), None, recurrence.as_deref())
                 {
                     Ok(task) => print_task(&task),
                     Err(e) => { eprintln!("Failed to create task: {}", e); std::process::exit(1); }
                 }
             }
-            TaskCommands::List { project, status, assignee, task_type, priority, limit } => {
+            TaskCommands::List { project, status, assignee, task_type, priority, ready, limit } => {
                 match db.list_tasks(project.as_deref(), status.as_deref(), assignee.as_deref(),
-                    task_type.as_deref(), priority.as_deref(), limit)
+                    task_type.as_deref(), priority.as_deref(), ready, limit)
                 {
                     Ok(tasks) => {
                         if tasks.is_empty() {
@@ -492,11 +1073,25 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
                                 }
                             }
                         }
+                        if let Ok(entries) = db.task_time_entries(id) {
+                            if !entries.is_empty() {
+                                let total: i64 = entries.iter().filter_map(|e| e.duration_seconds).sum();
+                                let running = entries.iter().filter(|e| e.ended_at.is_none()).count();
+                                let running = if running > 0 { format!(" ({} running)", running) } else { String::new() };
+                                println!("  Time tracked: {}{}", format_duration(total), running);
+                            }
+                        }
+                        if let Ok(comments) = db.list_task_comments(id) {
+                            if !comments.is_empty() {
+                                println!("  Comments:");
+                                print_comment_threads(&comments, "    ");
+                            }
+                        }
                     }
                     Err(e) => { eprintln!("Task not found: {}", e); std::process::exit(1); }
                 }
             }
-            TaskCommands::Update { id, status, subject, description, assignee, owner, priority, due } => {
+            TaskCommands::Update { id, status, subject, description, assignee, owner, priority, due, recurrence, no_recurrence, actor } => {
                 if let Some(ref s) = status {
                     if let Err(e) = s.parse::<TaskStatus>() { eprintln!("{}", e); std::process::exit(1); }
                 }
@@ -511,14 +1106,38 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
                 if let Some(v) = owner { updates.insert("owner".into(), serde_json::Value::String(v)); }
                 if let Some(v) = priority { updates.insert("priority".into(), serde_json::Value::String(v)); }
                 if let Some(v) = due { updates.insert("due_date".into(), serde_json::Value::String(v)); }
+                if let Some(v) = recurrence { updates.insert("recurrence".into(), serde_json::Value::String(v)); }
+                if no_recurrence { updates.insert("recurrence".into(), serde_json::Value::Null); }
+                if let Some(v) = actor { updates.insert("actor".into(), serde_json::Value::String(v)); }
                 let updates = serde_json::Value::Object(updates);
+                let before: Vec<(i64, String)> = db.get_task_deps(id)
+                    .map(|(_, dependents)| dependents.into_iter().map(|t| (t.id, t.status)).collect())
+                    .unwrap_or_default();
                 match db.update_task(id, &updates) {
-                    Ok(task) => { println!("Updated:"); print_task(&task); }
+                    Ok(task) => {
+                        println!("Updated:");
+                        print_task(&task);
+                        if let Ok((_, dependents)) = db.get_task_deps(id) {
+                            for t in &dependents {
+                                if before.iter().any(|(bid, status)| *bid == t.id && *status != t.status) {
+                                    let change = if t.status == "pending" { "Unblocked" } else { "Blocked" };
+                                    println!("{}: #{} {}", change, t.id, t.subject);
+                                }
+                            }
+                        }
+                        if task.status == "completed" {
+                            if let Ok(Some(next)) = db.next_occurrence(task.id) {
+                                println!("Next occurrence:");
+                                print_task(&next);
+                            }
+                        }
+                    }
                     Err(e) => { eprintln!("Update failed: {}", e); std::process::exit(1); }
                 }
             }
-            TaskCommands::Search { query, project, status, limit } => {
-                match db.search_tasks(&query, project.as_deref(), status.as_deref(), limit) {
+            TaskCommands::Search { query, project, status, limit, mode } => {
+                let mode = parse_search_mode(&mode);
+                match db.semantic_search_tasks(&query, project.as_deref(), status.as_deref(), limit, mode) {
                     Ok(tasks) => {
                         if tasks.is_empty() {
                             println!("No tasks found.");
@@ -556,6 +1175,21 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
                     Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
                 }
             }
+            TaskCommands::History { id } => {
+                if let Err(e) = db.get_task(id) {
+                    eprintln!("Task not found: {}", e);
+                    std::process::exit(1);
+                }
+                match db.task_events(id) {
+                    Ok(events) => {
+                        if events.is_empty() {
+                            println!("No history recorded for task {}.", id);
+                        }
+                        for ev in &events { print_task_event(ev); }
+                    }
+                    Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
+                }
+            }
             TaskCommands::AddDep { blocker, blocked } => {
                 match db.add_task_dep(blocker, blocked) {
                     Ok(()) => println!("Dependency added: task {} blocks task {}", blocker, blocked),
@@ -569,6 +1203,132 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
                     Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
                 }
             }
+            TaskCommands::CriticalPath { project } => {
+                match db.task_plan(&project) {
+                    Ok(plan) => {
+                        if plan.order.is_empty() {
+                            println!("No open tasks in project {}.", project);
+                        } else {
+                            println!("Order ({} open tasks, blockers first):", plan.order.len());
+                            for t in &plan.order { print_task_short(t); }
+                            println!("\nReady to start ({}):", plan.ready.len());
+                            for t in &plan.ready { print_task_short(t); }
+                            println!("\nCritical path ({} tasks):", plan.critical_path.len());
+                            let chain: Vec<String> = plan.critical_path.iter()
+                                .map(|t| format!("#{} {}", t.id, t.subject))
+                                .collect();
+                            println!("  {}", chain.join(" -> "));
+                        }
+                    }
+                    Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
+                }
+            }
+            TaskCommands::Comment { command: comment_cmd } => match comment_cmd {
+                CommentCommands::Add { task_id, body, author, reply_to } => {
+                    match db.add_task_comment(task_id, &body, author.as_deref(), reply_to, None) {
+                        Ok(c) => println!("Comment #{} added to task {}", c.id, c.task_id),
+                        Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
+                    }
+                }
+                CommentCommands::List { task_id } => {
+                    if let Err(e) = db.get_task(task_id) {
+                        eprintln!("Task not found: {}", e);
+                        std::process::exit(1);
+                    }
+                    match db.list_task_comments(task_id) {
+                        Ok(comments) => {
+                            if comments.is_empty() {
+                                println!("No comments on task {}.", task_id);
+                            } else {
+                                print_comment_threads(&comments, "");
+                                println!("\n({} comments)", comments.len());
+                            }
+                        }
+                        Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
+                    }
+                }
+                CommentCommands::Search { query, task, limit } => {
+                    match db.search_task_comments(&query, task, limit) {
+                        Ok(comments) => {
+                            if comments.is_empty() {
+                                println!("No comments found.");
+                            } else {
+                                for c in &comments {
+                                    println!("task #{}: {}", c.task_id, format_comment(c));
+                                }
+                                println!("\n({} results)", comments.len());
+                            }
+                        }
+                        Err(e) => { eprintln!("Search failed: {}", e); std::process::exit(1); }
+                    }
+                }
+            },
+            TaskCommands::Time { command: time_cmd } => match time_cmd {
+                TimeCommands::Start { task_id, session, actor, note } => {
+                    match db.start_task_timer(task_id, session.as_deref(), actor.as_deref(), note.as_deref()) {
+                        Ok(entry) => println!("Timer started on task {} at {} (entry {})", task_id, entry.started_at, entry.id),
+                        Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
+                    }
+                }
+                TimeCommands::Stop { task_id, session } => {
+                    match db.stop_task_timer(task_id, session.as_deref()) {
+                        Ok(entries) => {
+                            for entry in &entries { print_time_entry(entry); }
+                        }
+                        Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
+                    }
+                }
+                TimeCommands::Log { task_id, duration, started_at, session, actor, note } => {
+                    let seconds = match parse_duration(&duration) {
+                        Ok(s) => s,
+                        Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+                    };
+                    match db.log_task_time(task_id, seconds, started_at.as_deref(), session.as_deref(), actor.as_deref(), note.as_deref()) {
+                        Ok(entry) => print_time_entry(&entry),
+                        Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
+                    }
+                }
+                TimeCommands::List { task_id } => {
+                    if let Err(e) = db.get_task(task_id) {
+                        eprintln!("Task not found: {}", e);
+                        std::process::exit(1);
+                    }
+                    match db.task_time_entries(task_id) {
+                        Ok(entries) => {
+                            if entries.is_empty() {
+                                println!("No time tracked on task {}.", task_id);
+                            } else {
+                                for entry in &entries { print_time_entry(entry); }
+                                let total: i64 = entries.iter().filter_map(|e| e.duration_seconds).sum();
+                                println!("\nTotal: {}", format_duration(total));
+                            }
+                        }
+                        Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
+                    }
+                }
+                TimeCommands::Report { by, project, since } => {
+                    let group = match by.parse::<TimeGroup>() {
+                        Ok(g) => g,
+                        Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+                    };
+                    match db.time_report(group, project.as_deref(), since.as_deref()) {
+                        Ok(rows) => {
+                            if rows.is_empty() {
+                                println!("No tracked time.");
+                            } else {
+                                println!("{:<20} {:>8} {:>10}", group.to_string(), "entries", "time");
+                                for row in &rows {
+                                    println!("{:<20} {:>8} {:>10}",
+                                        row.group.as_deref().unwrap_or("(none)"), row.entries, format_duration(row.seconds));
+                                }
+                                let total: i64 = rows.iter().map(|r| r.seconds).sum();
+                                println!("\nTotal: {}", format_duration(total));
+                            }
+                        }
+                        Err(e) => { eprintln!("Failed: {}", e); std::process::exit(1); }
+                    }
+                }
+            },
         },
 
         Commands::Link { command: link_cmd } => match link_cmd {
@@ -600,6 +1360,66 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
             }
         },
 
+        Commands::Project { command } => match command {
+            ProjectCommands::List => match db.list_projects() {
+                Ok(projects) if projects.is_empty() => println!("No projects registered."),
+                Ok(projects) => {
+                    for p in &projects {
+                        println!("{}", p.name);
+                        if !p.aliases.is_empty() { println!("  Aliases: {}", p.aliases.join(", ")); }
+                        for remote in &p.git_remotes { println!("  Remote:  {}", remote); }
+                        for root in &p.git_roots { println!("  Root:    {}", root); }
+                    }
+                }
+                Err(e) => { eprintln!("List failed: {}", e); std::process::exit(1); }
+            },
+            ProjectCommands::Resolve { path } => {
+                let path = match path.map_or_else(std::env::current_dir, std::fs::canonicalize) {
+                    Ok(p) => p,
+                    Err(e) => { eprintln!("Cannot resolve path: {}", e); std::process::exit(1); }
+                };
+                println!("{}", projects::resolve(&db, &path.display().to_string()));
+            }
+            ProjectCommands::Alias { project, alias } => match db.add_project_alias(&project, &alias) {
+                Ok(p) => println!("'{}' now resolves to project '{}'.", alias, p.name),
+                Err(e) => { eprintln!("Alias failed: {}", e); std::process::exit(1); }
+            },
+            ProjectCommands::Unalias { alias } => match db.remove_project_alias(&alias) {
+                Ok(true) => println!("Alias '{}' removed.", alias),
+                Ok(false) => { eprintln!("Alias not found: {}", alias); std::process::exit(1); }
+                Err(e) => { eprintln!("Unalias failed: {}", e); std::process::exit(1); }
+            },
+            ProjectCommands::Rename { old, new } => match db.rename_project(&old, &new) {
+                Ok(r) => println!(
+                    "Renamed '{}' to '{}': {} tasks, {} conversation entries, {} tool calls updated.",
+                    old, new, r.tasks, r.conversations, r.tool_calls
+                ),
+                Err(e) => { eprintln!("Rename failed: {}", e); std::process::exit(1); }
+            },
+            ProjectCommands::Merge { from, into } => match db.merge_projects(&from, &into) {
+                Ok(r) => println!(
+                    "Merged '{}' into '{}': {} tasks, {} conversation entries, {} tool calls moved.",
+                    from, into, r.tasks, r.conversations, r.tool_calls
+                ),
+                Err(e) => { eprintln!("Merge failed: {}", e); std::process::exit(1); }
+            },
+        },
+
+        Commands::Scan { scrub } => match db.scan_secrets(scrub) {
+            Ok(found) if found.is_empty() => println!("No secrets found."),
+            Ok(found) => {
+                for m in &found {
+                    println!("{}.{} {}: {}", m.table, m.column, m.id, m.detectors.join(", "));
+                }
+                if scrub {
+                    println!("\nScrubbed {} values.", found.len());
+                } else {
+                    println!("\n{} values contain secrets. Run with --scrub to replace them.", found.len());
+                }
+            }
+            Err(e) => { eprintln!("Scan failed: {}", e); std::process::exit(1); }
+        },
+
         Commands::Backup { output, force } => {
             let output_path = std::path::Path::new(&output);
             if let Err(e) = crate::backup::run_backup(&db, output_path, force) {
@@ -611,7 +1431,7 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
         Commands::Archive { command: archive_cmd } => {
             let db_path_str = db_path.to_string_lossy().to_string();
             match archive_cmd {
-                ArchiveCommands::Create { output, entity_type, older_than, project, category, limit, purge, force } => {
+                ArchiveCommands::Create { output, entity_type, older_than, project, category, expired, limit, purge, force } => {
                     let valid_types = ["memories", "conversations", "tasks", "all"];
                     if !valid_types.contains(&entity_type.as_str()) {
                         eprintln!("Invalid entity type '{}'. Must be one of: {}", entity_type, valid_types.join(", "));
@@ -620,7 +1440,7 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
                     let output_path = std::path::Path::new(&output);
                     if let Err(e) = crate::archive::run_archive_create(
                         &db, &db_path_str, output_path, &entity_type,
-                        older_than, project.as_deref(), category.as_deref(), !purge, force, limit,
+                        older_than, project.as_deref(), category.as_deref(), expired, !purge, force, limit,
                     ) {
                         eprintln!("{}", e);
                         std::process::exit(1);
@@ -655,9 +1475,204 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
             }
         }
 
+        Commands::Embed { command: embed_cmd } => match embed_cmd {
+            EmbedCommands::Status => {
+                match db.embedder_model() {
+                    Some(model) => println!("Embedder: {}", model),
+                    None => println!("Embedder: off (set CLAUDE_MEMORY_EMBEDDER to enable)"),
+                }
+                match db.embedding_counts() {
+                    Ok(counts) => {
+                        if counts.is_empty() {
+                            println!("No stored vectors.");
+                        } else {
+                            println!("Stored vectors:");
+                            for (entity_type, model, count) in &counts {
+                                println!("  {:<14} {:<24} {}", entity_type, model, count);
+                            }
+                        }
+                    }
+                    Err(e) => { eprintln!("Status failed: {}", e); std::process::exit(1); }
+                }
+            }
+            EmbedCommands::Reindex { entity_type } => {
+                let valid_types = ["memories", "conversations", "tasks", "all"];
+                if !valid_types.contains(&entity_type.as_str()) {
+                    eprintln!("Invalid entity type '{}'. Must be one of: {}", entity_type, valid_types.join(", "));
+                    std::process::exit(1);
+                }
+                match db.reindex_embeddings(&entity_type) {
+                    Ok(count) => println!("Reindexed {} entities", count),
+                    Err(e) => { eprintln!("Reindex failed: {}", e); std::process::exit(1); }
+                }
+            }
+        },
+
+        Commands::Import { command: ImportCommands::Transcript { path, rescan, dry_run } } => {
+            let path = path.unwrap_or_else(|| {
+                let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+                PathBuf::from(home).join(".claude").join("projects")
+            });
+            let capture = load_config().capture;
+            match transcript::import_transcripts(&db, &path, &capture, rescan, dry_run) {
+                Ok(summary) => {
+                    if dry_run {
+                        println!("Found {} messages in {} transcripts (dry run, nothing stored)", summary.messages, summary.files);
+                    } else {
+                        println!("Imported {} new messages from {} transcripts ({} already stored)",
+                            summary.stored, summary.files, summary.messages - summary.stored);
+                        if summary.tool_calls > 0 {
+                            println!("  Recorded {} tool calls", summary.tool_calls);
+                        }
+                    }
+                    if summary.malformed > 0 {
+                        println!("  Skipped {} malformed lines", summary.malformed);
+                    }
+                    if summary.failed > 0 {
+                        println!("  {} transcripts could not be read", summary.failed);
+                    }
+                }
+                Err(e) => { eprintln!("Import failed: {}", e); std::process::exit(1); }
+            }
+        }
+
+        Commands::Recall { prompt, project, limit, tokens, min_score } => {
+            let mut config = match RecallConfig::load(&load_config().recall) {
+                Ok(c) => c,
+                Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+            };
+            config.limit = limit.unwrap_or(config.limit);
+            config.token_budget = tokens.unwrap_or(config.token_budget);
+            config.min_score = min_score.unwrap_or(config.min_score);
+            match recall::recall(&db, &prompt, project.as_deref(), &config) {
+                Ok(hits) if hits.is_empty() => println!("Nothing relevant found."),
+                Ok(hits) => {
+                    for hit in &hits {
+                        println!("{:.2}  {} {}: {}", hit.score, hit.source, hit.label, hit.text);
+                    }
+                    if !config.enabled {
+                        println!("\n(recall is off in hooks; set [recall] enabled = true or CLAUDE_MEMORY_RECALL=on to enable)");
+                    }
+                }
+                Err(e) => { eprintln!("Recall failed: {}", e); std::process::exit(1); }
+            }
+        }
+
+        Commands::Briefing { project, session_id, tokens, template } => {
+            let mut config = match BriefingConfig::load(&load_config().briefing) {
+                Ok(c) => c,
+                Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+            };
+            config.token_budget = tokens.unwrap_or(config.token_budget);
+            if let Some(path) = template {
+                config.template = match briefing::load_template(&path) {
+                    Ok(t) => t,
+                    Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+                };
+            }
+            match briefing::build(&db, &session_id, &project, &config) {
+                Ok(text) => println!("{}", text),
+                Err(e) => { eprintln!("Briefing failed: {}", e); std::process::exit(1); }
+            }
+        }
+
+        Commands::ToolCalls { command } => match command {
+            ToolCallCommands::List { session, project, tool, errors, since, limit } => {
+                let since = match since.as_deref().map(crate::db::resolve_since).transpose() {
+                    Ok(s) => s,
+                    Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+                };
+                let filter = ToolCallFilter { session_id: session, project, tool_name: tool, errors_only: errors, since };
+                match db.list_tool_calls(&filter, limit) {
+                    Ok(calls) if calls.is_empty() => println!("No tool calls found."),
+                    Ok(calls) => {
+                        for call in &calls {
+                            print_tool_call(call);
+                        }
+                    }
+                    Err(e) => { eprintln!("List failed: {}", e); std::process::exit(1); }
+                }
+            }
+            ToolCallCommands::Get { id } => match db.get_tool_call(id) {
+                Ok(call) => {
+                    print_tool_call(&call);
+                    if let Some(c) = call.conversation_id { println!("  Conversation entry: {}", c); }
+                    println!("  Input: {}", call.input.as_deref().unwrap_or(""));
+                    match &call.result {
+                        Some(r) => println!("  Result:\n{}", r),
+                        None => println!("  Result: (not recorded)"),
+                    }
+                }
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    eprintln!("Tool call {} not found", id);
+                    std::process::exit(1);
+                }
+                Err(e) => { eprintln!("Get failed: {}", e); std::process::exit(1); }
+            },
+            ToolCallCommands::Files { session, project } => {
+                match db.edited_files(session.as_deref(), project.as_deref()) {
+                    Ok(files) if files.is_empty() => println!("No edited files found."),
+                    Ok(files) => {
+                        for f in &files {
+                            let failed = if f.failed > 0 { format!(", {} failed", f.failed) } else { String::new() };
+                            println!("{} ({} edits{}, last {})", f.path, f.edits, failed,
+                                f.last_edited_at.as_deref().unwrap_or("unknown"));
+                        }
+                    }
+                    Err(e) => { eprintln!("Query failed: {}", e); std::process::exit(1); }
+                }
+            }
+            ToolCallCommands::Failures { project, since, limit } => {
+                match db.tool_failure_stats(project.as_deref(), since.as_deref(), limit) {
+                    Ok(stats) if stats.is_empty() => println!("No completed tool calls found."),
+                    Ok(stats) => {
+                        let width = stats.iter().map(|s| s.tool_name.len()).max().unwrap_or(0).max(4);
+                        println!("{:<width$}  {:>8}  {:>8}  {:>6}", "TOOL", "CALLS", "FAILED", "RATE", width = width);
+                        for s in &stats {
+                            println!("{:<width$}  {:>8}  {:>8}  {:>5.1}%", s.tool_name, s.calls, s.failures,
+                                s.failure_rate * 100.0, width = width);
+                        }
+                    }
+                    Err(e) => { eprintln!("Query failed: {}", e); std::process::exit(1); }
+                }
+            }
+        },
+
+        Commands::Stats { command: StatsCommands::Tokens { by, project, session, model, since, prices, json } } => {
+            let groups = match UsageGroup::parse_list(&by) {
+                Ok(g) => g,
+                Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+            };
+            let since = match since.as_deref().map(crate::db::resolve_since).transpose() {
+                Ok(s) => s,
+                Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+            };
+            let prices = match prices {
+                Some(path) => PriceTable::load(&path).map(Some),
+                None => PriceTable::from_env(),
+            };
+            let prices = match prices {
+                Ok(p) => p,
+                Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+            };
+            let filter = UsageFilter { project, session_id: session, model, since };
+            match db.token_usage(&groups, &filter) {
+                Ok(records) => {
+                    let report = usage::build_report(&groups, &records, prices.as_ref());
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+                    } else {
+                        print_usage_report(&report);
+                    }
+                }
+                Err(e) => { eprintln!("Stats failed: {}", e); std::process::exit(1); }
+            }
+        }
+
         Commands::Log { command: log_cmd } => match log_cmd {
-            LogCommands::Search { query, session, entry_type, limit } => {
-                match db.search_conversations(&query, session.as_deref(), entry_type.as_deref(), limit) {
+            LogCommands::Search { query, session, entry_type, limit, mode } => {
+                let mode = parse_search_mode(&mode);
+                match db.semantic_search_conversations(&query, session.as_deref(), entry_type.as_deref(), limit, mode) {
                     Ok(entries) => {
                         if entries.is_empty() {
                             println!("No conversations found.");
@@ -710,6 +1725,37 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
                     }
                 }
             }
+            LogCommands::Timeline { session_id, entry_type } => {
+                let types: Vec<String> = entry_type.iter()
+                    .flat_map(|t| t.split(','))
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect();
+                if let Some(bad) = types.iter().find_map(|t| t.parse::<EntryType>().err()) {
+                    eprintln!("{}", bad);
+                    std::process::exit(1);
+                }
+                match db.session_transcript(&session_id) {
+                    Ok(entries) => {
+                        let entries: Vec<_> = entries.iter()
+                            .filter(|e| types.is_empty() || e.entry_type.as_ref().is_some_and(|t| types.contains(t)))
+                            .collect();
+                        if entries.is_empty() {
+                            println!("No entries found for session.");
+                        }
+                        for entry in &entries {
+                            let line = entry.content.lines().next().unwrap_or("");
+                            println!("[{}] {:<13} {:<9} {}",
+                                entry.message_timestamp.as_deref().unwrap_or(&entry.created_at),
+                                entry.entry_type.as_deref().unwrap_or("-"), entry.role, truncate_chars(line, 160));
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Timeline failed: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
             LogCommands::Prune { older_than, entry_type } => {
                 if older_than <= 0 {
                     eprintln!("--older-than must be a positive number of days");
@@ -723,10 +1769,138 @@ pub fn run_cli(command: Commands, db_path: &PathBuf) {
                     }
                 }
             }
+            LogCommands::Dedupe { dry_run } => {
+                match db.dedupe_conversations(dry_run) {
+                    Ok(count) if dry_run => println!("Found {} duplicate entries (dry run, nothing removed)", count),
+                    Ok(count) => println!("Removed {} duplicate entries", count),
+                    Err(e) => {
+                        eprintln!("Dedupe failed: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
         },
     }
 }
 
+/// The configuration for the current directory; a bad file is an error here,
+/// unlike in the hook.
+fn load_config() -> Config {
+    let cwd = std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_default();
+    match Config::load(&cwd) {
+        Ok(loaded) => loaded.config,
+        Err(e) => { eprintln!("Invalid configuration: {}", e); std::process::exit(1); }
+    }
+}
+
+pub fn run_config(command: ConfigCommands) {
+    match command {
+        ConfigCommands::Show { dir } => {
+            let dir = match dir.map_or_else(std::env::current_dir, std::fs::canonicalize) {
+                Ok(d) => d,
+                Err(e) => { eprintln!("Cannot resolve path: {}", e); std::process::exit(1); }
+            };
+            let loaded = match Config::load(&dir.display().to_string()) {
+                Ok(l) => l,
+                Err(e) => { eprintln!("Invalid configuration: {}", e); std::process::exit(1); }
+            };
+            if loaded.sources.is_empty() {
+                println!("# No config files found; using defaults");
+            }
+            for source in &loaded.sources {
+                println!("# From {}", source.display());
+            }
+            match toml::to_string_pretty(&loaded.config) {
+                Ok(text) => print!("\n{}", text),
+                Err(e) => { eprintln!("Failed to render configuration: {}", e); std::process::exit(1); }
+            }
+        }
+        ConfigCommands::Validate { file } => {
+            let result = match &file {
+                Some(path) => std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+                    .and_then(|text| Config::from_toml(&text).map_err(|e| format!("{}: {}", path.display(), e)))
+                    .map(|_| vec![path.clone()]),
+                None => {
+                    let cwd = std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_default();
+                    Config::load(&cwd).map(|l| l.sources)
+                }
+            };
+            match result {
+                Ok(sources) if sources.is_empty() => println!("No config files found."),
+                Ok(sources) => {
+                    for source in &sources { println!("OK  {}", source.display()); }
+                }
+                Err(e) => { eprintln!("Invalid configuration: {}", e); std::process::exit(1); }
+            }
+        }
+    }
+}
+
+/// Migrations run against an unmigrated connection so `up --to N` can stop short of latest.
+pub fn run_migrate(command: MigrateCommands, db_path: &PathBuf) {
+    let db = match Database::open_unmigrated(db_path) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("Failed to open database at {:?}: {}", db_path, e);
+            std::process::exit(1);
+        }
+    };
+
+    match command {
+        MigrateCommands::Status => {
+            let current = match db.schema_version() {
+                Ok(v) => v,
+                Err(e) => { eprintln!("Status failed: {}", e); std::process::exit(1); }
+            };
+            let latest = crate::migrations::latest_version();
+            println!("Database: {}", db_path.display());
+            println!("Schema version: {} (latest: {})", current, latest);
+            if current > latest {
+                println!("  Warning: database is newer than this binary.");
+            }
+            match db.migration_status() {
+                Ok(statuses) => {
+                    for st in &statuses {
+                        match &st.applied_at {
+                            Some(ts) => println!("  [x] {:>4} {:<28} applied {}", st.version, st.name, ts),
+                            None => println!("  [ ] {:>4} {:<28} pending", st.version, st.name),
+                        }
+                    }
+                }
+                Err(e) => { eprintln!("Status failed: {}", e); std::process::exit(1); }
+            }
+        }
+        MigrateCommands::Up { to } => {
+            let target = to.unwrap_or_else(crate::migrations::latest_version);
+            match db.schema_version() {
+                Ok(current) if current > target => {
+                    eprintln!("Database is already at version {} (> {}). Down migrations are not supported.", current, target);
+                    std::process::exit(1);
+                }
+                Ok(_) => {}
+                Err(e) => { eprintln!("Migration failed: {}", e); std::process::exit(1); }
+            }
+            match db.migrate_to(target) {
+                Ok(applied) if applied.is_empty() => println!("Already up to date (version {}).", target),
+                Ok(applied) => {
+                    let list: Vec<String> = applied.iter().map(|v| v.to_string()).collect();
+                    println!("Applied migrations: {}", list.join(", "));
+                    println!("Schema version: {}", target);
+                }
+                Err(e) => { eprintln!("Migration failed: {}", e); std::process::exit(1); }
+            }
+        }
+    }
+}
+
+fn parse_search_mode(mode: &str) -> SearchMode {
+    match mode.parse::<SearchMode>() {
+        Ok(m) => m,
+        Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+    }
+}
+
 fn print_memory(mem: &crate::db::Memory) {
     println!("---");
     println!("[{}:{}] (id: {})", mem.category, mem.key, mem.id);
@@ -734,9 +1908,22 @@ fn print_memory(mem: &crate::db::Memory) {
     if let Some(tags) = &mem.tags {
         println!("  Tags: {}", tags.join(", "));
     }
+    if let Some(expires_at) = &mem.expires_at {
+        println!("  Expires: {}", expires_at);
+    }
     println!("  Updated: {}", mem.updated_at);
 }
 
+fn print_memory_revision(rev: &crate::db::MemoryRevision) {
+    println!("---");
+    println!("rev {} [{}:{}]", rev.rev, rev.category, rev.key);
+    println!("  {}", rev.value);
+    if let Some(tags) = &rev.tags {
+        println!("  Tags: {}", tags.join(", "));
+    }
+    println!("  Written: {}  Replaced: {}", rev.updated_at, rev.replaced_at);
+}
+
 fn print_conversation(entry: &crate::db::ConversationEntry) {
     println!("---");
     let etype = entry.entry_type.as_deref().unwrap_or("unknown");
@@ -752,6 +1939,20 @@ fn print_conversation(entry: &crate::db::ConversationEntry) {
     }
 }
 
+fn print_tool_call(call: &crate::db::ToolCall) {
+    let status = match (&call.result, call.is_error) {
+        (None, _) => "pending",
+        (Some(_), true) => "error",
+        (Some(_), false) => "ok",
+    };
+    let duration = call.duration_ms.map(|ms| format!(" {}ms", ms)).unwrap_or_default();
+    println!("#{} [{}] {} {}{} (session: {})", call.id, call.started_at.as_deref().unwrap_or(&call.created_at),
+        call.tool_name, status, duration, call.session_id);
+    if let Some(input) = &call.input {
+        println!("  {}", summarize_input(input, 120));
+    }
+}
+
 fn print_task(task: &crate::db::Task) {
     println!("---");
     println!("#{} [{}] {} ({})", task.id, task.status, task.subject, task.project);
@@ -763,6 +1964,7 @@ fn print_task(task: &crate::db::Task) {
     if let Some(a) = &task.assignee { println!("  Assignee: {}", a); }
     if let Some(o) = &task.owner { println!("  Owner: {}", o); }
     if let Some(d) = &task.due_date { println!("  Due: {}", d); }
+    if let Some(r) = &task.recurrence { println!("  Repeats: {}", r); }
     if let Some(pid) = &task.parent_id { println!("  Parent: #{}", pid); }
     if let Some(cb) = &task.created_by { println!("  Created by: {}", cb); }
     if let Some(sid) = &task.session_id { println!("  Session: {}", sid); }
@@ -778,6 +1980,101 @@ fn print_task_short(task: &crate::db::Task) {
         if ttype != "-" { format!("[{}] {}", ttype, task.subject) } else { task.subject.clone() });
 }
 
+fn print_task_event(ev: &crate::db::TaskEvent) {
+    let who: Vec<String> = [
+        ev.actor.as_ref().map(|a| format!("by {}", a)),
+        ev.session_id.as_ref().map(|s| format!("session {}", s)),
+    ].into_iter().flatten().collect();
+    let who = if who.is_empty() { String::new() } else { format!(" ({})", who.join(", ")) };
+    if ev.field == "created" {
+        println!("[{}] created{}", ev.created_at, who);
+    } else {
+        println!("[{}] {}: {} -> {}{}", ev.created_at, ev.field,
+            ev.old_value.as_deref().unwrap_or("(none)"),
+            ev.new_value.as_deref().unwrap_or("(none)"), who);
+    }
+}
+
+fn print_usage_report(report: &usage::UsageReport) {
+    if report.total.messages == 0 {
+        println!("No token usage recorded.");
+        return;
+    }
+    let priced = report.total.cost_usd.is_some();
+    let mut header: Vec<String> = report.group_by.clone();
+    header.extend(["messages", "input", "output", "cache write", "cache read", "hit %"].map(String::from));
+    if priced {
+        header.push("cost $".to_string());
+    }
+    let cells = |row: &usage::UsageRow, label: Option<&str>| -> Vec<String> {
+        let mut cells: Vec<String> = match label {
+            Some(l) => report.group_by.iter().enumerate()
+                .map(|(i, _)| if i == 0 { l.to_string() } else { String::new() })
+                .collect(),
+            None => report.group_by.iter()
+                .map(|g| row.group.get(g).cloned().flatten().unwrap_or_else(|| "(none)".to_string()))
+                .collect(),
+        };
+        cells.extend([
+            row.messages.to_string(),
+            row.input_tokens.to_string(),
+            row.output_tokens.to_string(),
+            row.cache_creation_tokens.to_string(),
+            row.cache_read_tokens.to_string(),
+            row.cache_hit_ratio.map_or("-".to_string(), |r| format!("{:.1}", r * 100.0)),
+        ]);
+        if priced {
+            cells.push(row.cost_usd.map_or("-".to_string(), |c| if c < 1.0 { format!("{:.4}", c) } else { format!("{:.2}", c) }));
+        }
+        cells
+    };
+    let mut table: Vec<Vec<String>> = vec![header];
+    table.extend(report.rows.iter().map(|r| cells(r, None)));
+    table.push(cells(&report.total, Some("TOTAL")));
+
+    let widths: Vec<usize> = (0..table[0].len())
+        .map(|i| table.iter().map(|row| row[i].len()).max().unwrap_or(0))
+        .collect();
+    let groups = report.group_by.len();
+    for (n, row) in table.iter().enumerate() {
+        if n == table.len() - 1 {
+            println!();
+        }
+        let line: Vec<String> = row.iter().enumerate()
+            .map(|(i, c)| if i < groups { format!("{:<w$}", c, w = widths[i]) } else { format!("{:>w$}", c, w = widths[i]) })
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+    if !report.total.unpriced_models.is_empty() {
+        println!("\nNo price for: {}", report.total.unpriced_models.join(", "));
+    }
+}
+
+fn print_time_entry(entry: &crate::db::TimeEntry) {
+    let duration = entry.duration_seconds.map_or("running".to_string(), format_duration);
+    let who: Vec<String> = [
+        entry.actor.as_ref().map(|a| format!("by {}", a)),
+        entry.session_id.as_ref().map(|s| format!("session {}", s)),
+    ].into_iter().flatten().collect();
+    let who = if who.is_empty() { String::new() } else { format!(" ({})", who.join(", ")) };
+    let note = entry.note.as_ref().map(|n| format!(" - {}", n)).unwrap_or_default();
+    println!("#{} task {} [{}] {}{}{}", entry.id, entry.task_id, entry.started_at, duration, who, note);
+}
+
+fn format_comment(c: &crate::db::TaskComment) -> String {
+    format!("#{} {} [{}]: {}", c.id, c.author.as_deref().unwrap_or("anonymous"), c.created_at, c.body)
+}
+
+/// Print comments (already in thread order), indenting replies under their parent.
+fn print_comment_threads(comments: &[crate::db::TaskComment], indent: &str) {
+    let mut depth: std::collections::HashMap<i64, usize> = std::collections::HashMap::new();
+    for c in comments {
+        let d = c.parent_id.and_then(|p| depth.get(&p)).map_or(0, |d| d + 1);
+        depth.insert(c.id, d);
+        println!("{}{}{}", indent, "  ".repeat(d), format_comment(c));
+    }
+}
+
 fn print_link(link: &crate::db::Link) {
     let rel = link.relation.as_deref().unwrap_or("linked");
     println!("---");
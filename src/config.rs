@@ -0,0 +1,327 @@
+//! Hook handler configuration, read from TOML files.
+//!
+//! The user file (`~/.claude/leafhill-memory.toml`, or `$CLAUDE_MEMORY_CONFIG`)
+//! is read first, then the project file (`.claude/leafhill-memory.toml` at the
+//! git root, else in the working directory) over it, key by key. Environment
+//! variables such as `CLAUDE_MEMORY_RECALL` override both. A project file
+//! comes with the repository, so its `[redaction]` can only add detectors and
+//! patterns to the user's.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::briefing;
+use crate::projects::ProjectLocation;
+use crate::redact::{self, Redactor};
+use crate::toolcalls::truncate_chars;
+
+pub const CONFIG_FILE: &str = "leafhill-memory.toml";
+
+/// Hook events that can be switched off under `[events]`.
+pub const HOOK_EVENTS: &[&str] = &[
+    "SessionStart", "UserPromptSubmit", "Stop", "SubagentStop", "SessionEnd",
+    "Notification", "PreToolUse", "PostToolUse", "PreCompact",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Event name to whether the hook handles it; unlisted events are on.
+    pub events: BTreeMap<String, bool>,
+    pub capture: CaptureConfig,
+    pub redaction: RedactionConfig,
+    pub briefing: BriefingSettings,
+    pub recall: RecallSettings,
+}
+
+/// How much of each event is stored.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    /// Longest prompt, response or transcript message stored, in chars;
+    /// 0 keeps everything.
+    pub max_content_chars: usize,
+    /// Longest tool input summary or response kept in a timeline entry; the
+    /// full text is in `tool_calls`.
+    pub max_tool_chars: usize,
+    /// Keep assistant thinking blocks from transcripts.
+    pub thinking: bool,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig { max_content_chars: 0, max_tool_chars: 2000, thinking: true }
+    }
+}
+
+impl CaptureConfig {
+    /// `text` cut to `max_content_chars`.
+    pub fn cap(&self, text: &str) -> String {
+        if self.max_content_chars == 0 { text.to_string() } else { truncate_chars(text, self.max_content_chars) }
+    }
+}
+
+/// Text replaced before memories, conversations and tool calls are stored.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactionConfig {
+    /// Built-in detectors to run (see `redact::DETECTORS`); all by default.
+    pub detectors: Vec<String>,
+    /// Regular expressions of your own; every match is replaced.
+    pub patterns: Vec<String>,
+    pub replacement: String,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        RedactionConfig {
+            detectors: redact::DETECTORS.iter().map(|(name, _)| name.to_string()).collect(),
+            patterns: Vec::new(),
+            replacement: redact::DEFAULT_REPLACEMENT.to_string(),
+        }
+    }
+}
+
+impl RedactionConfig {
+    pub fn compile(&self) -> Result<Redactor, String> {
+        Redactor::new(&self.detectors, &self.patterns, &self.replacement)
+    }
+}
+
+/// `[briefing]`: overrides for `briefing::BriefingConfig`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BriefingSettings {
+    pub tasks: Option<usize>,
+    pub summaries: Option<usize>,
+    pub pinned: Option<usize>,
+    pub tokens: Option<usize>,
+    /// Inline template.
+    pub template: Option<String>,
+    /// Template file, relative to the config file it is set in.
+    pub template_file: Option<PathBuf>,
+}
+
+/// `[recall]`: overrides for `recall::RecallConfig`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecallSettings {
+    pub enabled: Option<bool>,
+    pub limit: Option<usize>,
+    pub tokens: Option<usize>,
+    pub min_score: Option<f64>,
+}
+
+/// `[redaction]` in a project file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RedactionAdditions {
+    detectors: Vec<String>,
+    patterns: Vec<String>,
+}
+
+/// The effective configuration and the files it was read from.
+#[derive(Debug, Default)]
+pub struct LoadedConfig {
+    pub config: Config,
+    pub sources: Vec<PathBuf>,
+}
+
+impl Config {
+    pub fn event_enabled(&self, event: &str) -> bool {
+        self.events.get(event).copied().unwrap_or(true)
+    }
+
+    /// The redactor for `[redaction]`. A loaded config has been validated, so
+    /// this only falls back to the built-in detectors for a hand-built one.
+    pub fn redactor(&self) -> Redactor {
+        self.redaction.compile().unwrap_or_default()
+    }
+
+    /// Parse and check one config file's contents.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let config: Config = toml::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(event) = self.events.keys().find(|e| !HOOK_EVENTS.contains(&e.as_str())) {
+            return Err(format!("Unknown event '{}' in [events]. Must be one of: {}", event, HOOK_EVENTS.join(", ")));
+        }
+        self.redaction.compile()?;
+        if let Some(template) = &self.briefing.template {
+            briefing::validate_template(template)?;
+        }
+        if self.briefing.template.is_some() && self.briefing.template_file.is_some() {
+            return Err("Set either briefing.template or briefing.template_file, not both".to_string());
+        }
+        if self.recall.min_score.is_some_and(|s| !(0.0..=1.0).contains(&s)) {
+            return Err("recall.min_score must be from 0 to 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// The user file: `$CLAUDE_MEMORY_CONFIG`, else `~/.claude/leafhill-memory.toml`.
+    pub fn user_path() -> Option<PathBuf> {
+        match std::env::var("CLAUDE_MEMORY_CONFIG") {
+            Ok(p) if !p.trim().is_empty() => Some(PathBuf::from(p.trim())),
+            _ => std::env::var("HOME").ok().map(|h| PathBuf::from(h).join(".claude").join(CONFIG_FILE)),
+        }
+    }
+
+    /// The project file for `cwd`: `.claude/leafhill-memory.toml` at the git
+    /// root, else in `cwd` itself.
+    pub fn project_path(cwd: &str) -> Option<PathBuf> {
+        if cwd.is_empty() {
+            return None;
+        }
+        let root = ProjectLocation::detect(cwd).git_root.unwrap_or_else(|| cwd.to_string());
+        Some(Path::new(&root).join(".claude").join(CONFIG_FILE))
+    }
+
+    /// The configuration for a session in `cwd`: the user file, then the
+    /// project file over it. Missing files are skipped.
+    pub fn load(cwd: &str) -> Result<LoadedConfig, String> {
+        Self::load_files(Self::user_path(), Self::project_path(cwd))
+    }
+
+    fn load_files(user: Option<PathBuf>, project: Option<PathBuf>) -> Result<LoadedConfig, String> {
+        let mut merged = toml::Table::new();
+        let mut sources: Vec<PathBuf> = Vec::new();
+        let mut additions = RedactionAdditions::default();
+        for (path, is_project) in [(user, false), (project, true)] {
+            let Some(path) = path.filter(|p| p.is_file() && !sources.contains(p)) else { continue };
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let mut table: toml::Table = toml::from_str(&text)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            if let Some(redaction) = is_project.then(|| table.remove("redaction")).flatten() {
+                additions = redaction.try_into().map_err(|e: toml::de::Error| format!(
+                    "{}: [redaction] in a project file can only add detectors and patterns: {}", path.display(), e
+                ))?;
+            }
+            // A later file's template replaces an earlier one in either form
+            if let Some(toml::Value::Table(b)) = table.get("briefing") {
+                if b.contains_key("template") || b.contains_key("template_file") {
+                    if let Some(toml::Value::Table(m)) = merged.get_mut("briefing") {
+                        m.remove("template");
+                        m.remove("template_file");
+                    }
+                }
+            }
+            // A template file is relative to the config file naming it
+            if let Some(toml::Value::String(file)) = table.get_mut("briefing").and_then(|b| b.get_mut("template_file")) {
+                let dir = path.parent().unwrap_or(Path::new("."));
+                *file = dir.join(&*file).display().to_string();
+            }
+            merge_tables(&mut merged, table);
+            sources.push(path);
+        }
+        let mut config: Config = toml::Value::Table(merged).try_into().map_err(|e: toml::de::Error| e.to_string())?;
+        let redaction = &mut config.redaction;
+        for detector in additions.detectors {
+            if !redaction.detectors.contains(&detector) {
+                redaction.detectors.push(detector);
+            }
+        }
+        for pattern in additions.patterns {
+            if !redaction.patterns.contains(&pattern) {
+                redaction.patterns.push(pattern);
+            }
+        }
+        config.validate()?;
+        Ok(LoadedConfig { config, sources })
+    }
+
+    /// Like `load`, but never fails, since a hook must never block Claude
+    /// Code: errors are reported on stderr and the user file used alone, or
+    /// failing that the defaults. A broken project file thus cannot drop the
+    /// user's redaction settings.
+    pub fn load_or_default(cwd: &str) -> Config {
+        Self::load(cwd).map(|l| l.config).unwrap_or_else(|e| {
+            eprintln!("leafhill: ignoring configuration: {}", e);
+            Self::load_files(Self::user_path(), None).map(|l| l.config).unwrap_or_default()
+        })
+    }
+
+    /// `load_or_default` for the process's own working directory, as the CLI
+    /// and the MCP servers use.
+    pub fn load_for_current_dir() -> Config {
+        let cwd = std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_default();
+        Self::load_or_default(&cwd)
+    }
+}
+
+/// Merge `overlay` into `base`; nested tables merge key by key, anything
+/// else in `overlay` replaces what `base` had.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(b)), toml::Value::Table(o)) => merge_tables(b, o),
+            (_, value) => { base.insert(key, value); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_validate() {
+        let config = Config::from_toml(
+            "[events]\nPreToolUse = false\n\n[capture]\nthinking = false\nmax_content_chars = 10\n\n[recall]\nenabled = true\n"
+        ).unwrap();
+        assert!(!config.event_enabled("PreToolUse"));
+        assert!(config.event_enabled("Stop"));
+        assert_eq!(config.capture.max_tool_chars, 2000);
+        assert_eq!(config.capture.cap("0123456789abc").chars().count(), 10);
+        assert_eq!(config.recall.enabled, Some(true));
+
+        assert!(Config::from_toml("[events]\nPreToolCall = false\n").unwrap_err().contains("Unknown event 'PreToolCall'"));
+        assert!(Config::from_toml("[capture]\nthinkin = false\n").is_err());
+        assert!(Config::from_toml("[redaction]\npatterns = [\"(\"]\n").unwrap_err().contains("Invalid redaction pattern"));
+        let redactor = Config::from_toml("[redaction]\npatterns = [\"sk-[a-z0-9]+\", \"hunter2\"]\n")
+            .unwrap().redactor();
+        assert_eq!(redactor.redact("key sk-abc123 and hunter2 for a@b.io"), "key [REDACTED] and [REDACTED] for [REDACTED]");
+        let redactor = Config::from_toml("[redaction]\ndetectors = []\n").unwrap().redactor();
+        assert_eq!(redactor.redact("a@b.io"), "a@b.io");
+        assert!(Config::from_toml("[redaction]\ndetectors = [\"ssn\"]\n").unwrap_err().contains("Unknown redaction detector"));
+        assert!(Config::from_toml("[briefing]\ntemplate = \"{nope}\"\n").is_err());
+        assert!(Config::from_toml("[recall]\nmin_score = 3.0\n").is_err());
+    }
+
+    #[test]
+    fn test_project_file_overrides_user_file() {
+        let dir = std::env::temp_dir().join(format!("leafhill-config-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("project/.claude")).unwrap();
+        let user = dir.join("user.toml");
+        let project = dir.join("project/.claude").join(CONFIG_FILE);
+        std::fs::write(&user, "[capture]\nthinking = false\nmax_tool_chars = 500\n\n[events]\nNotification = false\n\n[briefing]\ntemplate = \"{project}\"\n").unwrap();
+        std::fs::write(&project, "[capture]\nmax_tool_chars = 100\n\n[briefing]\ntemplate_file = \"briefing.txt\"\n").unwrap();
+
+        let loaded = Config::load_files(Some(user.clone()), Some(project.clone())).unwrap();
+        assert_eq!(loaded.sources, [user.clone(), project.clone()]);
+        let c = loaded.config;
+        assert_eq!((c.capture.thinking, c.capture.max_tool_chars), (false, 100));
+        assert!(!c.event_enabled("Notification"));
+        assert_eq!(c.briefing.template_file, Some(dir.join("project/.claude/briefing.txt")));
+        assert_eq!(c.briefing.template, None);
+        assert_eq!(Config::load_files(Some(dir.join("missing.toml")), Some(project.clone())).unwrap().sources.len(), 1);
+
+        // A project file can add to the user's redaction but not take anything away
+        std::fs::write(&user, "[redaction]\ndetectors = [\"aws_key\"]\npatterns = [\"corp-[0-9]+\"]\n").unwrap();
+        std::fs::write(&project, "[redaction]\ndetectors = [\"jwt\"]\npatterns = [\"proj-[0-9]+\"]\n").unwrap();
+        let r = Config::load_files(Some(user.clone()), Some(project.clone())).unwrap().config.redaction;
+        assert_eq!((r.detectors, r.patterns), (vec!["aws_key".to_string(), "jwt".to_string()], vec!["corp-[0-9]+".to_string(), "proj-[0-9]+".to_string()]));
+        std::fs::write(&project, "[redaction]\ndetectors = []\npatterns = []\n").unwrap();
+        let r = Config::load_files(None, Some(project.clone())).unwrap().config.redaction;
+        assert_eq!(r, RedactionConfig::default());
+        std::fs::write(&project, "[redaction]\nreplacement = \"\"\n").unwrap();
+        assert!(Config::load_files(Some(user), Some(project)).unwrap_err().contains("can only add detectors and patterns"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,350 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A local, CPU-only text embedding backend.
+/// Vectors are L2-normalized so cosine similarity is a plain dot product.
+pub trait Embedder {
+    /// Identifier stored alongside each vector; vectors from different models are never compared.
+    fn model(&self) -> String;
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String>;
+}
+
+/// Build the embedder selected by `CLAUDE_MEMORY_EMBEDDER`.
+///
+/// - unset / `off` — embeddings disabled (FTS-only search)
+/// - `hash` or `hash:<dim>` — deterministic feature-hashing embedder
+/// - `vectors:<path>` — static word vectors (GloVe / word2vec text format)
+pub fn from_env() -> Option<Box<dyn Embedder>> {
+    let spec = std::env::var("CLAUDE_MEMORY_EMBEDDER").ok()?;
+    match from_spec(&spec) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("leafhill-persistent-memory: {}; embeddings disabled", e);
+            None
+        }
+    }
+}
+
+pub fn from_spec(spec: &str) -> Result<Option<Box<dyn Embedder>>, String> {
+    let spec = spec.trim();
+    if spec.is_empty() || spec == "off" {
+        return Ok(None);
+    }
+    if spec == "hash" {
+        return Ok(Some(Box::new(HashingEmbedder::new(HashingEmbedder::DEFAULT_DIM))));
+    }
+    if let Some(dim) = spec.strip_prefix("hash:") {
+        let dim: usize = dim.parse()
+            .map_err(|_| format!("Invalid embedder dimension '{}'", dim))?;
+        if dim == 0 {
+            return Err("Embedder dimension must be positive".to_string());
+        }
+        return Ok(Some(Box::new(HashingEmbedder::new(dim))));
+    }
+    if let Some(path) = spec.strip_prefix("vectors:") {
+        return Ok(Some(Box::new(StaticVectorEmbedder::new(PathBuf::from(path)))));
+    }
+    Err(format!("Invalid CLAUDE_MEMORY_EMBEDDER '{}'. Must be one of: off, hash, hash:<dim>, vectors:<path>", spec))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode { Fts, Hybrid, Semantic }
+
+impl FromStr for SearchMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fts" => Ok(Self::Fts),
+            "hybrid" => Ok(Self::Hybrid),
+            "semantic" => Ok(Self::Semantic),
+            _ => Err(format!("Invalid mode '{}'. Must be one of: fts, hybrid, semantic", s)),
+        }
+    }
+}
+
+impl fmt::Display for SearchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fts => write!(f, "fts"),
+            Self::Hybrid => write!(f, "hybrid"),
+            Self::Semantic => write!(f, "semantic"),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for x in v.iter_mut() {
+            *x /= norm;
+        }
+    }
+}
+
+/// FNV-1a: stable across platforms and Rust versions, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
+/// Signed feature hashing over word unigrams and character trigrams.
+/// Needs no model files; trigrams give partial credit to related word forms ("deploy"/"deployment").
+pub struct HashingEmbedder {
+    dim: usize,
+}
+
+impl HashingEmbedder {
+    pub const DEFAULT_DIM: usize = 256;
+
+    pub fn new(dim: usize) -> Self {
+        HashingEmbedder { dim }
+    }
+
+    fn add(&self, v: &mut [f32], feature: &str, weight: f32) {
+        let h = fnv1a(feature.as_bytes());
+        let idx = (h % self.dim as u64) as usize;
+        let sign = if (h >> 63) == 0 { 1.0 } else { -1.0 };
+        v[idx] += sign * weight;
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn model(&self) -> String {
+        format!("hash-{}", self.dim)
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        let mut v = vec![0.0f32; self.dim];
+        for token in tokenize(text) {
+            self.add(&mut v, &format!("w:{}", token), 1.0);
+            let padded: Vec<char> = format!("#{}#", token).chars().collect();
+            for tri in padded.windows(3) {
+                let tri: String = tri.iter().collect();
+                self.add(&mut v, &format!("c:{}", tri), 0.5);
+            }
+        }
+        normalize(&mut v);
+        Ok(v)
+    }
+}
+
+/// Word → vector table with its dimension.
+type VectorTable = (usize, HashMap<String, Vec<f32>>);
+
+/// Averages pre-trained static word vectors loaded from a local text file
+/// (`word v1 v2 ... vN` per line; a word2vec `count dim` header is skipped).
+/// The file is read lazily on first use so hooks that never embed stay fast.
+pub struct StaticVectorEmbedder {
+    path: PathBuf,
+    table: OnceCell<Result<VectorTable, String>>,
+}
+
+impl StaticVectorEmbedder {
+    pub fn new(path: PathBuf) -> Self {
+        StaticVectorEmbedder { path, table: OnceCell::new() }
+    }
+
+    fn load(&self) -> Result<VectorTable, String> {
+        let text = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read word vectors {}: {}", self.path.display(), e))?;
+        let mut dim = 0usize;
+        let mut table = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let mut parts = line.split_whitespace();
+            let word = match parts.next() {
+                Some(w) => w,
+                None => continue,
+            };
+            let values: Vec<f32> = parts.filter_map(|p| p.parse().ok()).collect();
+            if i == 0 && values.len() == 1 {
+                continue; // word2vec header
+            }
+            if dim == 0 {
+                dim = values.len();
+            }
+            if values.len() != dim || dim == 0 {
+                return Err(format!("{}:{}: expected {} dimensions, found {}", self.path.display(), i + 1, dim, values.len()));
+            }
+            table.insert(word.to_lowercase(), values);
+        }
+        if table.is_empty() {
+            return Err(format!("No word vectors found in {}", self.path.display()));
+        }
+        Ok((dim, table))
+    }
+
+    fn table(&self) -> Result<&VectorTable, String> {
+        self.table.get_or_init(|| self.load()).as_ref().map_err(|e| e.clone())
+    }
+}
+
+impl Embedder for StaticVectorEmbedder {
+    fn model(&self) -> String {
+        let name = self.path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string());
+        format!("vectors:{}", name)
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        let (dim, table) = self.table()?;
+        let mut v = vec![0.0f32; *dim];
+        for token in tokenize(text) {
+            if let Some(wv) = table.get(&token) {
+                for (acc, x) in v.iter_mut().zip(wv) {
+                    *acc += x;
+                }
+            }
+        }
+        normalize(&mut v);
+        Ok(v)
+    }
+}
+
+pub fn to_blob(v: &[f32]) -> Vec<u8> {
+    v.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn from_blob(b: &[u8]) -> Vec<f32> {
+    b.chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Dot product of two normalized vectors; 0.0 when dimensions differ.
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Rewrite free text as an FTS5 query matching any of its terms.
+/// Natural-language queries would otherwise require every word and can hit FTS syntax errors.
+pub fn fts_any_terms(query: &str) -> String {
+    tokenize(query)
+        .iter()
+        .map(|t| format!("\"{}\"", t))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Reciprocal-rank fusion constant (Cormack et al. use 60).
+const RRF_K: f32 = 60.0;
+
+/// Merge an FTS (BM25) ranking with a cosine ranking into a single id list.
+/// `semantic` is expected sorted by descending similarity.
+pub fn fuse(fts: &[i64], semantic: &[(i64, f32)], mode: SearchMode, limit: usize) -> Vec<i64> {
+    match mode {
+        SearchMode::Fts => fts.iter().copied().take(limit).collect(),
+        SearchMode::Semantic => semantic.iter()
+            .filter(|(_, score)| *score > 0.0)
+            .map(|(id, _)| *id)
+            .take(limit)
+            .collect(),
+        SearchMode::Hybrid => {
+            let mut scores: HashMap<i64, f32> = HashMap::new();
+            for (rank, id) in fts.iter().enumerate() {
+                *scores.entry(*id).or_default() += 1.0 / (RRF_K + rank as f32 + 1.0);
+            }
+            for (rank, (id, score)) in semantic.iter().enumerate() {
+                if *score > 0.0 {
+                    *scores.entry(*id).or_default() += 1.0 / (RRF_K + rank as f32 + 1.0);
+                }
+            }
+            let mut ranked: Vec<(i64, f32)> = scores.into_iter().collect();
+            ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+            ranked.into_iter().map(|(id, _)| id).take(limit).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashing_embedder_deterministic_and_normalized() {
+        let e = HashingEmbedder::new(64);
+        let a = e.embed("Release process for production").unwrap();
+        let b = e.embed("Release process for production").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 64);
+        assert!((cosine(&a, &a) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_hashing_embedder_similarity() {
+        let e = HashingEmbedder::new(256);
+        let q = e.embed("how do we deploy").unwrap();
+        let near = e.embed("deployment steps for the service").unwrap();
+        let far = e.embed("favourite colour is green").unwrap();
+        assert!(cosine(&q, &near) > cosine(&q, &far));
+    }
+
+    #[test]
+    fn test_static_vector_embedder() {
+        let dir = std::env::temp_dir().join(format!("leafhill-vectors-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("vectors.txt");
+        std::fs::write(&path, "3 2\ndeploy 1.0 0.0\nrelease 0.9 0.1\ncolour 0.0 1.0\n").unwrap();
+
+        let e = StaticVectorEmbedder::new(path);
+        assert_eq!(e.model(), "vectors:vectors.txt");
+        let q = e.embed("how do we deploy").unwrap();
+        let release = e.embed("release-process").unwrap();
+        let colour = e.embed("colour").unwrap();
+        assert!(cosine(&q, &release) > 0.9);
+        assert!(cosine(&q, &colour) < 0.1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_blob_roundtrip() {
+        let v = vec![0.25f32, -1.5, 3.0];
+        assert_eq!(from_blob(&to_blob(&v)), v);
+    }
+
+    #[test]
+    fn test_from_spec() {
+        assert!(from_spec("off").unwrap().is_none());
+        assert_eq!(from_spec("hash").unwrap().unwrap().model(), "hash-256");
+        assert_eq!(from_spec("hash:32").unwrap().unwrap().model(), "hash-32");
+        assert!(from_spec("hash:0").is_err());
+        assert!(from_spec("onnx").is_err());
+    }
+
+    #[test]
+    fn test_fts_any_terms() {
+        assert_eq!(fts_any_terms("how do we deploy?"), "\"how\" OR \"do\" OR \"we\" OR \"deploy\"");
+        assert_eq!(fts_any_terms("  "), "");
+    }
+
+    #[test]
+    fn test_fuse_hybrid_rewards_agreement() {
+        let fts = vec![1, 2, 3];
+        let sem = vec![(3, 0.9), (4, 0.8), (1, 0.1)];
+        let ids = fuse(&fts, &sem, SearchMode::Hybrid, 10);
+        assert_eq!(ids[0], 1);
+        assert_eq!(ids[1], 3);
+        assert!(ids.contains(&4));
+
+        assert_eq!(fuse(&fts, &sem, SearchMode::Fts, 2), vec![1, 2]);
+        assert_eq!(fuse(&fts, &sem, SearchMode::Semantic, 2), vec![3, 4]);
+    }
+}
@@ -1,21 +1,14 @@
 use serde_json::Value;
-use std::io::{self, BufRead, Read};
-use std::path::PathBuf;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
-use crate::db::{Database, PreCompactMessage};
-
-/// Extract project name from cwd path.
-fn project_from_cwd(cwd: &str) -> &str {
-    let p = cwd.rsplit('/').next().unwrap_or("unknown0");
-    if p.is_empty() { "unknown0" } else { p }
-}
-
-/// Derive a formatted session_id from hook JSON fields.
-/// Format: {session_id}-{project_name}
-/// No timestamp — hooks are independent processes and timestamps would differ.
-fn derive_session_id(session_id: &str, cwd: &str) -> String {
-    format!("{}-{}", session_id, project_from_cwd(cwd))
-}
+use crate::briefing::{self, BriefingConfig};
+use crate::config::Config;
+use crate::db::{Database, ToolResultRecord, ToolUseRecord};
+use crate::projects;
+use crate::recall::{self, RecallConfig};
+use crate::toolcalls::{summarize_input, truncate_chars};
+use crate::transcript::{self, derive_session_id, project_from_cwd, SessionSource};
 
 pub fn handle_hook(db_path: &PathBuf) {
     let mut input = String::new();
@@ -42,18 +35,37 @@ pub fn handle_hook(db_path: &PathBuf) {
         .and_then(|v| v.as_str())
         .unwrap_or("");
 
+    let config = Config::load_or_default(cwd);
+    if !config.event_enabled(event) {
+        return;
+    }
+    let redactor = config.redactor();
+    // Redact before capping, so a cut cannot leave half a secret undetected
+    let clean = |text: &str| config.capture.cap(&redactor.redact(text));
+
+    let db = match Database::open(db_path) {
+        Ok(mut db) => {
+            db.set_redactor(redactor.clone());
+            Some(db)
+        }
+        Err(e) => { eprintln!("leafhill-hook: db error: {}", e); None }
+    };
+    let project = match &db {
+        Some(db) => projects::resolve(db, cwd),
+        None => project_from_cwd(cwd).to_string(),
+    };
+    let project = project.as_str();
+    let formatted_sid = derive_session_id(session_id, project);
+    let field = |name: &str| hook.get(name).and_then(|v| v.as_str()).unwrap_or("");
+
     match event {
         "SessionStart" => {
-            let formatted_sid = derive_session_id(session_id, cwd);
-            let project = project_from_cwd(cwd);
+            let source = field("source");
+            log_event(db.as_ref(), event, &formatted_sid, "system",
+                &format!("Session started ({})", if source.is_empty() { "startup" } else { source }),
+                project, "session_start");
             let output = serde_json::json!({
-                "additionalContext": format!(
-                    "[leafhill-memory] session_id={} project={}. \
-                     After EVERY exchange, call log_conversation with this session_id, \
-                     role=\"summary\", entry_type=\"summary\", and a concise summary of \
-                     what was discussed/done.",
-                    formatted_sid, project
-                )
+                "additionalContext": session_briefing(db.as_ref(), &formatted_sid, project, &config)
             });
             println!("{}", serde_json::to_string(&output).unwrap_or_default());
         }
@@ -62,18 +74,8 @@ pub fn handle_hook(db_path: &PathBuf) {
                 .map(|v| v.as_str().unwrap_or(&v.to_string()).to_string())
                 .unwrap_or_default();
             if prompt.is_empty() { return; }
-            let formatted_sid = derive_session_id(session_id, cwd);
-            let project = project_from_cwd(cwd);
-            let db = match Database::open(db_path) {
-                Ok(db) => db,
-                Err(e) => { eprintln!("leafhill-hook: db error: {}", e); return; }
-            };
-            if let Err(e) = db.log_conversation(
-                &formatted_sid, "user", &prompt,
-                Some(project), Some("raw_user"), None,
-            ) {
-                eprintln!("leafhill-hook: failed to log UserPromptSubmit: {}", e);
-            }
+            log_event(db.as_ref(), event, &formatted_sid, "user", &clean(&prompt), project, "raw_user");
+            recall_for_prompt(db.as_ref(), &prompt, project, &config);
         }
         "Stop" => {
             let stop_active = hook.get("stop_hook_active")
@@ -84,17 +86,89 @@ pub fn handle_hook(db_path: &PathBuf) {
                 .map(|v| v.as_str().unwrap_or(&v.to_string()).to_string())
                 .unwrap_or_default();
             if message.is_empty() { return; }
-            let formatted_sid = derive_session_id(session_id, cwd);
-            let project = project_from_cwd(cwd);
-            let db = match Database::open(db_path) {
-                Ok(db) => db,
-                Err(e) => { eprintln!("leafhill-hook: db error: {}", e); return; }
+            log_event(db.as_ref(), event, &formatted_sid, "assistant", &clean(&message), project, "raw_assistant");
+        }
+        "SubagentStop" => {
+            let stop_active = hook.get("stop_hook_active")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if stop_active { return; }
+            // Older Claude Code versions only pass the subagent's transcript
+            let message = match field("last_assistant_message") {
+                "" => Some(field("agent_transcript_path"))
+                    .filter(|p| !p.is_empty())
+                    .and_then(|p| transcript::last_assistant_message(Path::new(p)))
+                    .unwrap_or_default(),
+                m => m.to_string(),
+            };
+            if message.is_empty() { return; }
+            let content = match field("agent_type") {
+                "" => message,
+                agent => format!("[{}] {}", agent, message),
+            };
+            log_event(db.as_ref(), event, &formatted_sid, "assistant", &clean(&content), project, "subagent");
+        }
+        "SessionEnd" => {
+            let reason = field("reason");
+            log_event(db.as_ref(), event, &formatted_sid, "system",
+                &format!("Session ended ({})", if reason.is_empty() { "other" } else { reason }),
+                project, "session_end");
+        }
+        "Notification" => {
+            let message = field("message");
+            if message.is_empty() { return; }
+            log_event(db.as_ref(), event, &formatted_sid, "system", &clean(message), project, "notification");
+        }
+        "PreToolUse" | "PostToolUse" => {
+            let tool_name = match field("tool_name") {
+                "" => return,
+                name => name,
             };
-            if let Err(e) = db.log_conversation(
-                &formatted_sid, "assistant", &message,
-                Some(project), Some("raw_assistant"), None,
-            ) {
-                eprintln!("leafhill-hook: failed to log Stop: {}", e);
+            let input = hook.get("tool_input").map(|v| redactor.redact(&v.to_string())).unwrap_or_else(|| "{}".to_string());
+            let max_chars = config.capture.max_tool_chars;
+            let tool_use_id = field("tool_use_id");
+            let now = Some(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+            let Some(db) = &db else { return };
+
+            let (content, role, entry_type, stored) = if event == "PreToolUse" {
+                let use_record = ToolUseRecord {
+                    session_id: formatted_sid.clone(),
+                    project: project.to_string(),
+                    tool_use_id: tool_use_id.to_string(),
+                    tool_name: tool_name.to_string(),
+                    input: input.clone(),
+                    message_uuid: None,
+                    timestamp: now,
+                };
+                let stored = if tool_use_id.is_empty() { Ok(0) } else { db.store_tool_calls(&[use_record], &[]) };
+                let content = format!("{}: {}", tool_name, summarize_input(&input, max_chars));
+                (content, "assistant", "tool_use", stored)
+            } else {
+                let response = hook.get("tool_response").cloned().unwrap_or(Value::Null);
+                let is_error = response.get("is_error").and_then(|v| v.as_bool()).unwrap_or(false)
+                    || response.get("success").and_then(|v| v.as_bool()) == Some(false);
+                let text = redactor.redact(&match &response {
+                    Value::String(s) => s.clone(),
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                });
+                let result_record = ToolResultRecord {
+                    tool_use_id: tool_use_id.to_string(),
+                    result: text.clone(),
+                    is_error,
+                    message_uuid: None,
+                    timestamp: now,
+                };
+                let stored = if tool_use_id.is_empty() { Ok(0) } else { db.store_tool_calls(&[], &[result_record]) };
+                let status = if is_error { "error" } else { "ok" };
+                let content = format!("{} {}: {}", tool_name, status, truncate_chars(&text, max_chars));
+                (content, "tool", "tool_result", stored)
+            };
+            if let Err(e) = stored {
+                eprintln!("leafhill-hook: failed to record {} tool call: {}", event, e);
+            }
+            if let Err(e) = db.log_conversation(&formatted_sid, role, &content, Some(project), Some(entry_type), None) {
+                eprintln!("leafhill-hook: failed to log {}: {}", event, e);
             }
         }
         "PreCompact" => {
@@ -108,103 +182,17 @@ pub fn handle_hook(db_path: &PathBuf) {
                 }
             };
 
-            let file = match std::fs::File::open(transcript_path) {
-                Ok(f) => f,
-                Err(e) => {
-                    eprintln!("leafhill-hook: PreCompact: cannot open transcript: {}", e);
-                    return;
-                }
-            };
-
-            let formatted_sid = derive_session_id(session_id, cwd);
-            let project = project_from_cwd(cwd).to_string();
-            let mut messages: Vec<PreCompactMessage> = Vec::new();
-
-            let reader = io::BufReader::new(file);
-            for line in reader.lines() {
-                let line = match line {
-                    Ok(l) => l,
-                    Err(e) => {
-                        eprintln!("leafhill-hook: PreCompact: read error: {}", e);
-                        continue;
+            let Some(db) = &db else { return };
+            let source = SessionSource::Hook { session_id, project };
+            match transcript::ingest_transcript(db, Path::new(transcript_path), source, &config.capture, false, false) {
+                Ok(ingested) => {
+                    if ingested.malformed > 0 {
+                        eprintln!("leafhill-hook: PreCompact: skipped {} malformed JSONL lines", ingested.malformed);
                     }
-                };
-                if line.trim().is_empty() { continue; }
-
-                let event_obj: Value = match serde_json::from_str(&line) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        eprintln!("leafhill-hook: PreCompact: malformed JSONL line: {}", e);
-                        continue;
-                    }
-                };
-
-                let event_type = event_obj.get("type").and_then(|v| v.as_str()).unwrap_or("");
-                if event_type != "user" && event_type != "assistant" {
-                    continue;
-                }
-
-                let message = match event_obj.get("message") {
-                    Some(m) => m,
-                    None => continue,
-                };
-
-                let role = message.get("role")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or(event_type)
-                    .to_string();
-
-                let content = extract_content(message);
-                if content.is_empty() { continue; }
-
-                let model = message.get("model")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
-
-                let usage = message.get("usage");
-                let input_tokens = usage
-                    .and_then(|u| u.get("input_tokens"))
-                    .and_then(|v| v.as_i64());
-                let output_tokens = usage
-                    .and_then(|u| u.get("output_tokens"))
-                    .and_then(|v| v.as_i64());
-                let cache_creation_tokens = usage
-                    .and_then(|u| u.get("cache_creation_input_tokens"))
-                    .and_then(|v| v.as_i64());
-                let cache_read_tokens = usage
-                    .and_then(|u| u.get("cache_read_input_tokens"))
-                    .and_then(|v| v.as_i64());
-
-                let message_timestamp = event_obj.get("timestamp")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
-
-                messages.push(PreCompactMessage {
-                    session_id: formatted_sid.clone(),
-                    role,
-                    content,
-                    project: project.clone(),
-                    model,
-                    input_tokens,
-                    output_tokens,
-                    cache_creation_tokens,
-                    cache_read_tokens,
-                    message_timestamp,
-                });
-            }
-
-            if messages.is_empty() { return; }
-
-            let db = match Database::open(db_path) {
-                Ok(db) => db,
-                Err(e) => { eprintln!("leafhill-hook: db error: {}", e); return; }
-            };
-            match db.store_pre_compact_batch(&messages) {
-                Ok(count) => {
-                    eprintln!("leafhill-hook: PreCompact: stored {} messages", count);
+                    eprintln!("leafhill-hook: PreCompact: stored {} messages, {} tool calls", ingested.stored, ingested.tool_calls);
                 }
                 Err(e) => {
-                    eprintln!("leafhill-hook: PreCompact: db write failed: {}", e);
+                    eprintln!("leafhill-hook: PreCompact: {}", e);
                 }
             }
         }
@@ -214,144 +202,52 @@ pub fn handle_hook(db_path: &PathBuf) {
     }
 }
 
-/// Extract text content from a transcript message.
-/// For string content: return as-is.
-/// For content arrays: extract text and thinking blocks, skip tool_use/tool_result.
-pub(crate) fn extract_content(message: &Value) -> String {
-    let content = match message.get("content") {
-        Some(c) => c,
-        None => return String::new(),
-    };
-
-    // String content (typical for user messages)
-    if let Some(s) = content.as_str() {
-        return s.to_string();
-    }
+/// The SessionStart `additionalContext`. If the configuration or database is
+/// unusable, falls back to the template without any sections, so the
+/// session id still reaches Claude.
+fn session_briefing(db: Option<&Database>, session_id: &str, project: &str, settings: &Config) -> String {
+    let config = BriefingConfig::load(&settings.briefing).unwrap_or_else(|e| {
+        eprintln!("leafhill-hook: briefing config: {}", e);
+        BriefingConfig::default()
+    });
+    let built = db
+        .ok_or_else(|| "no database".to_string())
+        .and_then(|db| briefing::build(db, session_id, project, &config).map_err(|e| e.to_string()));
+    built.unwrap_or_else(|e| {
+        eprintln!("leafhill-hook: briefing failed: {}", e);
+        briefing::without_sections(&config.template, session_id, project)
+    })
+}
 
-    // Array content (assistant messages, tool results)
-    if let Some(arr) = content.as_array() {
-        let mut parts: Vec<String> = Vec::new();
-        for block in arr {
-            let block_type = block.get("type").and_then(|v| v.as_str()).unwrap_or("");
-            match block_type {
-                "text" => {
-                    if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
-                        parts.push(text.to_string());
-                    }
-                }
-                "thinking" => {
-                    if let Some(thinking) = block.get("thinking").and_then(|v| v.as_str()) {
-                        parts.push(format!("[thinking] {}", thinking));
-                    }
-                }
-                "tool_result" => {
-                    // User tool_result: serialize as JSON for completeness
-                    if let Some(result_content) = block.get("content") {
-                        if let Some(s) = result_content.as_str() {
-                            parts.push(s.to_string());
-                        } else {
-                            parts.push(result_content.to_string());
-                        }
-                    }
+/// With recall enabled, print memories, open tasks and summaries relevant
+/// to `prompt` as the hook's `additionalContext`.
+fn recall_for_prompt(db: Option<&Database>, prompt: &str, project: &str, settings: &Config) {
+    let config = match RecallConfig::load(&settings.recall) {
+        Ok(c) if c.enabled => c,
+        Ok(_) => return,
+        Err(e) => { eprintln!("leafhill-hook: recall disabled: {}", e); return; }
+    };
+    let Some(db) = db else { return };
+    match recall::recall(db, prompt, Some(project), &config) {
+        Ok(hits) if hits.is_empty() => {}
+        Ok(hits) => {
+            let output = serde_json::json!({
+                "hookSpecificOutput": {
+                    "hookEventName": "UserPromptSubmit",
+                    "additionalContext": recall::format_context(&hits)
                 }
-                // Skip tool_use blocks
-                _ => {}
-            }
+            });
+            println!("{}", serde_json::to_string(&output).unwrap_or_default());
         }
-        return parts.join("\n");
+        Err(e) => eprintln!("leafhill-hook: recall failed: {}", e),
     }
-
-    // Fallback: serialize whatever it is
-    content.to_string()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
-
-    // ── extract_content tests ────────────────────────────────────────
-
-    #[test]
-    fn test_extract_content_string() {
-        let msg = json!({"content": "hello world"});
-        assert_eq!(extract_content(&msg), "hello world");
-    }
-
-    #[test]
-    fn test_extract_content_text_and_thinking() {
-        let msg = json!({
-            "content": [
-                {"type": "text", "text": "response text"},
-                {"type": "thinking", "thinking": "internal thought"},
-                {"type": "tool_use", "name": "bash", "input": {}}
-            ]
-        });
-        let result = extract_content(&msg);
-        assert!(result.contains("response text"));
-        assert!(result.contains("[thinking] internal thought"));
-        assert!(!result.contains("bash")); // tool_use skipped
-    }
-
-    #[test]
-    fn test_extract_content_tool_result() {
-        let msg = json!({
-            "content": [
-                {"type": "tool_result", "content": "tool output text"}
-            ]
-        });
-        assert_eq!(extract_content(&msg), "tool output text");
-    }
-
-    #[test]
-    fn test_extract_content_tool_result_object() {
-        let msg = json!({
-            "content": [
-                {"type": "tool_result", "content": {"key": "value"}}
-            ]
-        });
-        let result = extract_content(&msg);
-        assert!(result.contains("key"));
-        assert!(result.contains("value"));
-    }
-
-    #[test]
-    fn test_extract_content_missing() {
-        let msg = json!({"role": "user"});
-        assert_eq!(extract_content(&msg), "");
-    }
-
-    #[test]
-    fn test_extract_content_empty_array() {
-        let msg = json!({"content": []});
-        assert_eq!(extract_content(&msg), "");
-    }
-
-    // ── derive_session_id tests ──────────────────────────────────────
-
-    #[test]
-    fn test_derive_session_id_format() {
-        let result = derive_session_id("abc123", "/home/user/myproject");
-        assert_eq!(result, "abc123-myproject");
-    }
-
-    // ── project_from_cwd tests ───────────────────────────────────────
-
-    #[test]
-    fn test_project_from_cwd_normal() {
-        assert_eq!(project_from_cwd("/home/user/myproject"), "myproject");
-    }
-
-    #[test]
-    fn test_project_from_cwd_trailing_slash() {
-        // rsplit('/') on trailing slash gives empty first, then the dir name
-        let result = project_from_cwd("/home/user/myproject/");
-        // With trailing slash, rsplit('/').next() is "", which maps to "unknown0"
-        assert_eq!(result, "unknown0");
-    }
-
-    #[test]
-    fn test_project_from_cwd_empty() {
-        assert_eq!(project_from_cwd(""), "unknown0");
+/// Log one conversation entry for a hook event. Failures are reported on
+/// stderr only, since a hook must never block Claude Code.
+fn log_event(db: Option<&Database>, event: &str, session_id: &str, role: &str, content: &str, project: &str, entry_type: &str) {
+    let Some(db) = db else { return };
+    if let Err(e) = db.log_conversation(session_id, role, content, Some(project), Some(entry_type), None) {
+        eprintln!("leafhill-hook: failed to log {}: {}", event, e);
     }
 }
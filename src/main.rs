@@ -1,10 +1,28 @@
+#![recursion_limit = "256"]
+
 mod archive;
 mod backup;
+mod briefing;
 mod cli;
+mod config;
 mod db;
+mod embedding;
 mod hook;
 mod mcp;
+mod mcp_http;
+mod migrations;
+mod projects;
+mod prompts;
+mod recall;
 mod rdbms_export;
+mod recurrence;
+mod redact;
+mod resources;
+mod taskgraph;
+mod timetrack;
+mod toolcalls;
+mod transcript;
+mod usage;
 
 use clap::Parser;
 use cli::{Cli, Commands};
@@ -24,14 +42,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let db_path = get_db_path();
 
     match cli.command {
-        Commands::Serve => {
-            mcp::serve(&db_path)?;
+        Commands::Serve { http } => {
+            match http {
+                Some(addr) => mcp_http::serve_http(&db_path, &addr)?,
+                None => mcp::serve(&db_path)?,
+            }
             Ok(())
         }
         Commands::HookHandler => {
             hook::handle_hook(&db_path);
             Ok(())
         }
+        Commands::Migrate { command } => {
+            cli::run_migrate(command, &db_path);
+            Ok(())
+        }
+        Commands::Config { command } => {
+            cli::run_config(command);
+            Ok(())
+        }
         other => {
             cli::run_cli(other, &db_path);
             Ok(())
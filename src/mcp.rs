@@ -2,15 +2,31 @@ use serde_json::{json, Value};
 use std::io::{self, BufRead, Write};
 use std::path::PathBuf;
 
-use crate::db::{Database, TaskStatus, TaskPriority, TaskType, EntryType};
+use crate::config::Config;
+use crate::db::{Database, Task, TaskStatus, TaskPriority, TaskType, EntryType};
+use crate::embedding::SearchMode;
+use crate::prompts;
+use crate::resources::{self, Subscriptions};
+use crate::timetrack::{parse_duration, TimeGroup};
+use crate::toolcalls::ToolCallFilter;
+use crate::usage::{self, PriceTable, UsageFilter, UsageGroup};
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// MCP JSON-RPC server over stdio.
-/// Handles initialize, tools/list, tools/call, and notifications.
+/// Handles initialize, tools/*, resources/*, and notifications.
 pub fn serve(db_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-    let db = Database::open(db_path)?;
+    let mut db = Database::open(db_path)?;
+    db.set_redactor(Config::load_for_current_dir().redactor());
     let stdin = io::stdin();
-    let stdout = io::stdout();
-    let mut stdout = stdout.lock();
+    let subscriptions = Arc::new(Subscriptions::default());
+
+    // Changes made by other processes (hooks, CLI) are picked up by the poller
+    resources::spawn_poller(db_path, Arc::clone(&subscriptions), |notification| {
+        if let Err(e) = write_message(&notification) {
+            eprintln!("leafhill-persistent-memory: failed to send notification: {}", e);
+        }
+    });
 
     eprintln!("leafhill-persistent-memory: MCP server started (db: {:?})", db_path);
 
@@ -21,60 +37,261 @@ pub fn serve(db_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
             continue;
         }
 
-        let request: Value = match serde_json::from_str(line) {
-            Ok(v) => v,
+        let response = match serde_json::from_str::<Value>(line) {
+            Ok(payload) => handle_payload(&payload, &db, &subscriptions),
             Err(e) => {
                 eprintln!("leafhill-persistent-memory: invalid JSON: {}", e);
-                continue;
+                Some(parse_error(&e))
             }
         };
+        if let Some(response) = response {
+            write_message(&response)?;
+        }
 
-        // Notifications have no "id" — don't respond
-        if request.get("id").is_none() {
-            let method = request.get("method").and_then(|m| m.as_str()).unwrap_or("");
-            eprintln!("leafhill-persistent-memory: notification: {}", method);
-            continue;
+        // Report changes made by this request right away
+        for uri in subscriptions.poll(&db) {
+            write_message(&resources::updated_notification(&uri))?;
         }
+    }
 
-        let id = request["id"].clone();
-        let method = request.get("method").and_then(|m| m.as_str()).unwrap_or("");
+    eprintln!("leafhill-persistent-memory: stdin closed, shutting down");
+    Ok(())
+}
 
-        let response = match method {
-            "initialize" => handle_initialize(&id),
-            "tools/list" => handle_tools_list(&id),
-            "tools/call" => handle_tools_call(&id, &request["params"], &db),
-            "ping" => json!({
-                "jsonrpc": "2.0",
-                "id": id,
-                "result": {}
-            }),
-            _ => json!({
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// A JSON-RPC error for methods that fail outside of a tool result.
+#[derive(Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn invalid_params(message: String) -> Self {
+        RpcError { code: INVALID_PARAMS, message, data: None }
+    }
+
+    pub fn db(e: rusqlite::Error) -> Self {
+        RpcError { code: INTERNAL_ERROR, message: format!("DB error: {}", e), data: None }
+    }
+
+    fn invalid_request(message: &str) -> Self {
+        RpcError { code: INVALID_REQUEST, message: format!("Invalid Request: {}", message), data: None }
+    }
+}
+
+/// Why a tool call failed. Bad arguments (missing fields, invalid enum
+/// values, DB validation errors) are a protocol error (-32602); anything
+/// else is a tool execution error reported in the result with `isError`.
+#[derive(Debug)]
+pub enum ToolError {
+    InvalidParams(String),
+    Failed(String),
+}
+
+impl From<&str> for ToolError {
+    fn from(msg: &str) -> Self {
+        ToolError::InvalidParams(msg.to_string())
+    }
+}
+
+impl From<String> for ToolError {
+    fn from(msg: String) -> Self {
+        ToolError::InvalidParams(msg)
+    }
+}
+
+/// Classify a DB error: `validation_err` failures become invalid params.
+fn db_error(context: &str, e: rusqlite::Error) -> ToolError {
+    match e {
+        rusqlite::Error::InvalidParameterName(msg) => ToolError::InvalidParams(msg),
+        e => ToolError::Failed(format!("{}: {}", context, e)),
+    }
+}
+
+/// Structured `data` for an invalid-params message, so clients can tell
+/// which argument was wrong without parsing text.
+fn invalid_params_data(message: &str) -> Value {
+    if let Some(field) = message.strip_prefix("missing '").and_then(|r| r.strip_suffix('\'')) {
+        return json!({ "kind": "missing_argument", "field": field });
+    }
+    // "Invalid <field> '<value>'. Must be one of: a, b, c"
+    if let Some((field, rest)) = message.strip_prefix("Invalid ").and_then(|r| r.split_once(" '")) {
+        if let Some((value, rest)) = rest.split_once('\'') {
+            let allowed: Option<Vec<&str>> = rest.split_once("Must be one of: ")
+                .map(|(_, list)| list.split(',').map(|a| a.trim()).collect());
+            return json!({ "kind": "invalid_value", "field": field, "value": value, "allowed": allowed });
+        }
+    }
+    json!({ "kind": "validation" })
+}
+
+fn parse_error(e: &serde_json::Error) -> Value {
+    respond(&Value::Null, Err(RpcError {
+        code: PARSE_ERROR,
+        message: format!("Parse error: {}", e),
+        data: None,
+    }))
+}
+
+/// Wrap a method result in a JSON-RPC response.
+fn respond(id: &Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result
+        }),
+        Err(e) => {
+            let mut error = json!({ "code": e.code, "message": e.message });
+            if let Some(data) = e.data {
+                error["data"] = data;
+            }
+            json!({
                 "jsonrpc": "2.0",
                 "id": id,
-                "error": {
-                    "code": -32601,
-                    "message": format!("Method not found: {}", method)
-                }
-            }),
-        };
+                "error": error
+            })
+        }
+    }
+}
 
-        let response_str = serde_json::to_string(&response)?;
-        writeln!(stdout, "{}", response_str)?;
-        stdout.flush()?;
+/// Write one newline-delimited JSON-RPC message to stdout.
+fn write_message(message: &Value) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", serde_json::to_string(message)?)?;
+    stdout.flush()
+}
+
+/// Dispatch a parsed JSON-RPC payload: a single message or a batch array.
+/// Returns `None` when nothing needs a reply (notifications only).
+pub fn handle_payload(payload: &Value, db: &Database, subscriptions: &Subscriptions) -> Option<Value> {
+    match payload {
+        Value::Array(items) if items.is_empty() => {
+            Some(respond(&Value::Null, Err(RpcError::invalid_request("empty batch"))))
+        }
+        Value::Array(items) => {
+            let responses: Vec<Value> = items.iter()
+                .filter_map(|m| handle_message(m, db, subscriptions))
+                .collect();
+            if responses.is_empty() { None } else { Some(Value::Array(responses)) }
+        }
+        _ => handle_message(payload, db, subscriptions),
     }
+}
 
-    eprintln!("leafhill-persistent-memory: stdin closed, shutting down");
-    Ok(())
+/// Check the JSON-RPC 2.0 envelope. Returns the method and the id
+/// (`None` for notifications).
+fn validate_request(request: &Value) -> Result<(&str, Option<Value>), RpcError> {
+    let obj = request.as_object()
+        .ok_or_else(|| RpcError::invalid_request("expected an object"))?;
+    if obj.get("jsonrpc").and_then(|v| v.as_str()) != Some("2.0") {
+        return Err(RpcError::invalid_request("'jsonrpc' must be \"2.0\""));
+    }
+    let id = match obj.get("id") {
+        None => None,
+        Some(id @ (Value::String(_) | Value::Number(_) | Value::Null)) => Some(id.clone()),
+        Some(_) => return Err(RpcError::invalid_request("'id' must be a string, number or null")),
+    };
+    let method = obj.get("method").and_then(|m| m.as_str())
+        .ok_or_else(|| RpcError::invalid_request("missing 'method'"))?;
+    if let Some(params) = obj.get("params") {
+        if !params.is_object() && !params.is_array() {
+            return Err(RpcError::invalid_request("'params' must be an object or array"));
+        }
+    }
+    Ok((method, id))
 }
 
-fn handle_initialize(id: &Value) -> Value {
+/// Dispatch a single JSON-RPC message. Returns `None` for notifications,
+/// which get no response. Shared by the stdio and HTTP transports.
+pub fn handle_message(request: &Value, db: &Database, subscriptions: &Subscriptions) -> Option<Value> {
+    // Replies to server-initiated requests; we never send any, so drop them
+    if request.get("method").is_none()
+        && (request.get("result").is_some() || request.get("error").is_some())
+    {
+        return None;
+    }
+
+    let (method, id) = match validate_request(request) {
+        Ok(v) => v,
+        Err(e) => {
+            let id = match request.get("id") {
+                Some(id @ (Value::String(_) | Value::Number(_))) => id.clone(),
+                _ => Value::Null,
+            };
+            return Some(respond(&id, Err(e)));
+        }
+    };
+
+    // Notifications have no "id" — don't respond
+    let id = match id {
+        Some(id) => id,
+        None => {
+            eprintln!("leafhill-persistent-memory: notification: {}", method);
+            return None;
+        }
+    };
+
+    let response = match method {
+        "initialize" => handle_initialize(&id, &request["params"]),
+        "tools/list" => handle_tools_list(&id),
+        "tools/call" => handle_tools_call(&id, &request["params"], db),
+        "resources/list" | "resources/read" | "resources/templates/list"
+        | "resources/subscribe" | "resources/unsubscribe" => {
+            handle_resources(&id, method, &request["params"], db, subscriptions)
+        }
+        "prompts/list" => respond(&id, Ok(prompts::list())),
+        "prompts/get" => {
+            let params = &request["params"];
+            let result = match params.get("name").and_then(|v| v.as_str()) {
+                Some(name) => prompts::get(db, name, params.get("arguments").unwrap_or(&Value::Null)),
+                None => Err(RpcError::invalid_params("missing 'name'".to_string())),
+            };
+            respond(&id, result)
+        }
+        "ping" => respond(&id, Ok(json!({}))),
+        _ => respond(&id, Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("Method not found: {}", method),
+            data: None,
+        })),
+    };
+    Some(response)
+}
+
+/// Protocol revisions this server speaks, newest first.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Echo the client's requested revision if we support it, otherwise offer
+/// our latest and let the client decide whether to continue.
+fn negotiate_protocol_version(params: &Value) -> &'static str {
+    let requested = params.get("protocolVersion").and_then(|v| v.as_str());
+    SUPPORTED_PROTOCOL_VERSIONS.iter()
+        .find(|v| Some(**v) == requested)
+        .copied()
+        .unwrap_or(SUPPORTED_PROTOCOL_VERSIONS[0])
+}
+
+fn handle_initialize(id: &Value, params: &Value) -> Value {
     json!({
         "jsonrpc": "2.0",
         "id": id,
         "result": {
-            "protocolVersion": "2024-11-05",
+            "protocolVersion": negotiate_protocol_version(params),
             "capabilities": {
-                "tools": {}
+                "tools": {},
+                "resources": {
+                    "subscribe": true,
+                    "listChanged": false
+                },
+                "prompts": {}
             },
             "serverInfo": {
                 "name": "leafhill-persistent-memory",
@@ -85,7 +302,49 @@ fn handle_initialize(id: &Value) -> Value {
     })
 }
 
+fn handle_resources(
+    id: &Value,
+    method: &str,
+    params: &Value,
+    db: &Database,
+    subscriptions: &Subscriptions,
+) -> Value {
+    let uri = params.get("uri").and_then(|v| v.as_str());
+    let result = match method {
+        "resources/list" => {
+            resources::list(db, params.get("cursor").and_then(|v| v.as_str()))
+        }
+        "resources/templates/list" => Ok(resources::templates()),
+        _ => match uri {
+            None => Err(RpcError::invalid_params("missing 'uri'".to_string())),
+            Some(uri) => match method {
+                "resources/read" => resources::read(db, uri),
+                "resources/subscribe" => subscriptions.subscribe(db, uri).map(|_| json!({})),
+                _ => {
+                    subscriptions.unsubscribe(uri);
+                    Ok(json!({}))
+                }
+            },
+        },
+    };
+
+    respond(id, result)
+}
+
 fn handle_tools_list(id: &Value) -> Value {
+    let mut response = tool_definitions(id);
+    if let Some(tools) = response["result"]["tools"].as_array_mut() {
+        for tool in tools {
+            let name = tool["name"].as_str().unwrap_or("").to_string();
+            if let Some(schema) = output_schema(&name) {
+                tool["outputSchema"] = schema;
+            }
+        }
+    }
+    response
+}
+
+fn tool_definitions(id: &Value) -> Value {
     json!({
         "jsonrpc": "2.0",
         "id": id,
@@ -113,6 +372,14 @@ fn handle_tools_list(id: &Value) -> Value {
                                 "type": "array",
                                 "items": { "type": "string" },
                                 "description": "Optional tags for classification"
+                            },
+                            "ttl": {
+                                "type": "string",
+                                "description": "Optional time-to-live, e.g. '12h', '7d', '2w'. Expired memories are hidden from list/search and removed by `gc`."
+                            },
+                            "expires_at": {
+                                "type": "string",
+                                "description": "Optional absolute expiry (RFC 3339, or 'YYYY-MM-DD HH:MM:SS' UTC). Mutually exclusive with ttl."
                             }
                         },
                         "required": ["category", "key", "value"]
@@ -120,7 +387,7 @@ fn handle_tools_list(id: &Value) -> Value {
                 },
                 {
                     "name": "search_memories",
-                    "description": "Search memories using full-text search. Returns matching memories ranked by relevance.",
+                    "description": "Search memories using full-text search, optionally fused with embedding similarity (mode=hybrid). Returns matching memories ranked by relevance.",
                     "inputSchema": {
                         "type": "object",
                         "properties": {
@@ -132,9 +399,17 @@ fn handle_tools_list(id: &Value) -> Value {
                                 "type": "string",
                                 "description": "Optional category filter"
                             },
+                            "mode": {
+                                "type": "string",
+                                "description": "fts (default), hybrid (BM25 + embedding similarity), or semantic (embedding similarity only). hybrid/semantic require CLAUDE_MEMORY_EMBEDDER."
+                            },
                             "limit": {
                                 "type": "integer",
                                 "description": "Max results (default 20)"
+                            },
+                            "include_expired": {
+                                "type": "boolean",
+                                "description": "Include memories whose expiry has passed (default false)"
                             }
                         },
                         "required": ["query"]
@@ -153,6 +428,10 @@ fn handle_tools_list(id: &Value) -> Value {
                             "limit": {
                                 "type": "integer",
                                 "description": "Max results (default 50)"
+                            },
+                            "include_expired": {
+                                "type": "boolean",
+                                "description": "Include memories whose expiry has passed (default false)"
                             }
                         },
                         "required": []
@@ -176,6 +455,46 @@ fn handle_tools_list(id: &Value) -> Value {
                         "required": ["category", "key"]
                     }
                 },
+                {
+                    "name": "memory_history",
+                    "description": "List prior versions of a memory (newest first). A revision is captured every time store_memory overwrites a value or delete_memory removes it.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "category": {
+                                "type": "string",
+                                "description": "Category of the memory"
+                            },
+                            "key": {
+                                "type": "string",
+                                "description": "Key of the memory"
+                            }
+                        },
+                        "required": ["category", "key"]
+                    }
+                },
+                {
+                    "name": "revert_memory",
+                    "description": "Restore a memory to a prior revision from memory_history. The current value is kept as a new revision, so reverts can be undone.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "category": {
+                                "type": "string",
+                                "description": "Category of the memory"
+                            },
+                            "key": {
+                                "type": "string",
+                                "description": "Key of the memory"
+                            },
+                            "rev": {
+                                "type": "integer",
+                                "description": "Revision number to restore"
+                            }
+                        },
+                        "required": ["category", "key", "rev"]
+                    }
+                },
                 {
                     "name": "log_conversation",
                     "description": "Log a conversation entry for persistent history. Record significant user/assistant exchanges.",
@@ -200,7 +519,7 @@ fn handle_tools_list(id: &Value) -> Value {
                             },
                             "entry_type": {
                                 "type": "string",
-                                "description": "Entry type: 'summary', 'raw_user', or 'raw_assistant'. Default: 'summary' when called by Claude."
+                                "description": "Entry type: 'summary', 'raw_user', or 'raw_assistant'. Default: 'summary' when called by Claude. Hooks also record 'pre_compact', 'session_start', 'session_end', 'subagent', 'tool_use', 'tool_result' and 'notification'."
                             },
                             "raw_id": {
                                 "type": "integer",
@@ -226,7 +545,11 @@ fn handle_tools_list(id: &Value) -> Value {
                             },
                             "entry_type": {
                                 "type": "string",
-                                "description": "Filter by entry type: 'summary', 'raw_user', 'raw_assistant'. Omit to search all."
+                                "description": "Filter by entry type: 'summary', 'raw_user', 'raw_assistant', 'pre_compact', 'session_start', 'session_end', 'subagent', 'tool_use', 'tool_result', 'notification'. Omit to search all."
+                            },
+                            "mode": {
+                                "type": "string",
+                                "description": "fts (default), hybrid (BM25 + embedding similarity), or semantic (embedding similarity only). hybrid/semantic require CLAUDE_MEMORY_EMBEDDER."
                             },
                             "limit": {
                                 "type": "integer",
@@ -270,14 +593,15 @@ fn handle_tools_list(id: &Value) -> Value {
                             "created_by": { "type": "string", "description": "Session ID or human name/email" },
                             "assignee": { "type": "string", "description": "Who does the work" },
                             "owner": { "type": "string", "description": "Who owns/approves the task" },
-                            "session_id": { "type": "string", "description": "Claude session ID" }
+                            "session_id": { "type": "string", "description": "Claude session ID" },
+                            "recurrence": { "type": "string", "description": "Repeat on completion: daily, weekly, monthly, yearly, or an RRULE subset (FREQ, INTERVAL, BYDAY, BYMONTHDAY), e.g. 'FREQ=WEEKLY;BYDAY=MO'" }
                         },
                         "required": ["project", "subject"]
                     }
                 },
                 {
                     "name": "update_task",
-                    "description": "Update a task's fields. Only provided fields are changed.",
+                    "description": "Update a task's fields. Only provided fields are changed. Completing a recurring task creates its next occurrence, returned as next_occurrence. Closing or reopening a task moves its dependents between blocked and pending; those changes are returned as unblocked / blocked.",
                     "inputSchema": {
                         "type": "object",
                         "properties": {
@@ -290,14 +614,16 @@ fn handle_tools_list(id: &Value) -> Value {
                             "assignee": { "type": "string", "description": "New assignee" },
                             "owner": { "type": "string", "description": "New owner" },
                             "due_date": { "type": "string", "description": "New due date (YYYY-MM-DD)" },
-                            "session_id": { "type": "string", "description": "Claude session that last touched this" }
+                            "session_id": { "type": "string", "description": "Claude session that last touched this" },
+                            "recurrence": { "type": ["string", "null"], "description": "New recurrence rule, or null to stop recurring" },
+                            "actor": { "type": "string", "description": "Who is making the change (human name/email or agent), recorded in the task's history" }
                         },
                         "required": ["task_id"]
                     }
                 },
                 {
                     "name": "get_task",
-                    "description": "Get a task by ID with its dependencies and linked entities.",
+                    "description": "Get a task by ID with its dependencies, linked entities and change history (events: who changed which field, when, from which session).",
                     "inputSchema": {
                         "type": "object",
                         "properties": {
@@ -317,6 +643,7 @@ fn handle_tools_list(id: &Value) -> Value {
                             "assignee": { "type": "string", "description": "Filter by assignee" },
                             "task_type": { "type": "string", "description": "Filter by type: claude, human, hybrid" },
                             "priority": { "type": "string", "description": "Filter by priority" },
+                            "ready": { "type": "boolean", "description": "Only actionable tasks: pending or in_progress with every blocker completed" },
                             "limit": { "type": "integer", "description": "Max results (default 50)" }
                         },
                         "required": []
@@ -331,6 +658,7 @@ fn handle_tools_list(id: &Value) -> Value {
                             "query": { "type": "string", "description": "Search query" },
                             "project": { "type": "string", "description": "Filter by project" },
                             "status": { "type": "string", "description": "Filter by status" },
+                            "mode": { "type": "string", "description": "fts (default), hybrid, or semantic" },
                             "limit": { "type": "integer", "description": "Max results (default 20)" }
                         },
                         "required": ["query"]
@@ -371,6 +699,164 @@ fn handle_tools_list(id: &Value) -> Value {
                         "required": ["blocker_id", "blocked_id"]
                     }
                 },
+                {
+                    "name": "add_task_comment",
+                    "description": "Comment on a task, optionally replying to an earlier comment on the same task to start a thread.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "task_id": { "type": "integer", "description": "Task ID" },
+                            "body": { "type": "string", "description": "Comment text" },
+                            "author": { "type": "string", "description": "Who is commenting (human name/email or agent)" },
+                            "reply_to": { "type": "integer", "description": "Comment ID this replies to" },
+                            "session_id": { "type": "string", "description": "Claude session the comment was written in" }
+                        },
+                        "required": ["task_id", "body"]
+                    }
+                },
+                {
+                    "name": "list_task_comments",
+                    "description": "List a task's comments in thread order (replies follow the comment they answer), or full-text search comments with 'query'.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "task_id": { "type": "integer", "description": "Task ID (required unless 'query' is given)" },
+                            "query": { "type": "string", "description": "Full-text search over comment bodies and authors" },
+                            "limit": { "type": "integer", "description": "Max search results (default 20)" }
+                        },
+                        "required": []
+                    }
+                },
+                {
+                    "name": "start_task_timer",
+                    "description": "Start tracking time on a task. Each session can have one running timer per task; completing or deleting the task stops it.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "task_id": { "type": "integer", "description": "Task ID" },
+                            "session_id": { "type": "string", "description": "Claude session doing the work" },
+                            "actor": { "type": "string", "description": "Who is working (human name/email or agent)" },
+                            "note": { "type": "string", "description": "What the time is for" }
+                        },
+                        "required": ["task_id"]
+                    }
+                },
+                {
+                    "name": "stop_task_timer",
+                    "description": "Stop the running timer a session started on a task. Without session_id, stops every running timer on the task.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "task_id": { "type": "integer", "description": "Task ID" },
+                            "session_id": { "type": "string", "description": "Session whose timer to stop" }
+                        },
+                        "required": ["task_id"]
+                    }
+                },
+                {
+                    "name": "log_task_time",
+                    "description": "Record time already spent on a task, without a timer.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "task_id": { "type": "integer", "description": "Task ID" },
+                            "duration": { "type": "string", "description": "Time spent, e.g. 45m, 1h30m (bare numbers are seconds)" },
+                            "started_at": { "type": "string", "description": "When the work started (RFC 3339 or YYYY-MM-DD HH:MM:SS UTC); defaults to duration before now" },
+                            "session_id": { "type": "string", "description": "Claude session that did the work" },
+                            "actor": { "type": "string", "description": "Who did the work" },
+                            "note": { "type": "string", "description": "What the time was for" }
+                        },
+                        "required": ["task_id", "duration"]
+                    }
+                },
+                {
+                    "name": "time_report",
+                    "description": "Total tracked time grouped by project, assignee, task_type (claude/human/hybrid) or source (claude = recorded in a Claude session, human = otherwise). Running timers are not counted.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "group_by": { "type": "string", "description": "project (default), assignee, task_type or source" },
+                            "project": { "type": "string", "description": "Only count tasks in this project" },
+                            "since": { "type": "string", "description": "Only count entries started at or after this date/time" }
+                        },
+                        "required": []
+                    }
+                },
+                {
+                    "name": "critical_path",
+                    "description": "Analyze a project's open tasks and their dependencies: a topological order (blockers first), the tasks ready to start now, and the longest chain of dependent open tasks.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "project": { "type": "string", "description": "Project to analyze" }
+                        },
+                        "required": ["project"]
+                    }
+                },
+                {
+                    "name": "token_stats",
+                    "description": "Aggregate token usage recorded with conversation messages by project, session, model, day and/or week, with cache hit ratios and, given a price table, estimated cost in USD.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "group_by": {
+                                "type": "array",
+                                "items": { "type": "string", "enum": UsageGroup::ALL },
+                                "description": "Grouping dimensions, e.g. [\"project\", \"week\"] (default [\"project\"])"
+                            },
+                            "project": { "type": "string", "description": "Only count this project" },
+                            "session_id": { "type": "string", "description": "Only count this session" },
+                            "model": { "type": "string", "description": "Only count this model" },
+                            "since": { "type": "string", "description": "Only count messages since an age (e.g. 7d) or date (e.g. 2026-01-01)" },
+                            "prices": {
+                                "type": "object",
+                                "description": "USD per million tokens keyed by model name or prefix: {\"claude-sonnet-4\": {\"input\": 3, \"output\": 15, \"cache_write\": 3.75, \"cache_read\": 0.3}}. Defaults to the file at CLAUDE_MEMORY_PRICES."
+                            }
+                        },
+                        "required": []
+                    }
+                },
+                {
+                    "name": "list_tool_calls",
+                    "description": "List tool calls captured from session transcripts (tool name, input JSON, result, error flag, timing), most recent first.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "session_id": { "type": "string", "description": "Only calls from this session" },
+                            "project": { "type": "string", "description": "Only calls in this project" },
+                            "tool_name": { "type": "string", "description": "Only calls of this tool, e.g. Bash or Edit" },
+                            "errors_only": { "type": "boolean", "description": "Only calls whose result was an error" },
+                            "since": { "type": "string", "description": "Only calls since an age (e.g. 7d) or date (e.g. 2026-01-01)" },
+                            "limit": { "type": "integer", "description": "Max results (default 20)" }
+                        },
+                        "required": []
+                    }
+                },
+                {
+                    "name": "edited_files",
+                    "description": "Files written by Edit, MultiEdit, Write and NotebookEdit tool calls, most recently edited first, e.g. to see which files a session changed.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "session_id": { "type": "string", "description": "Only edits from this session" },
+                            "project": { "type": "string", "description": "Only edits in this project" }
+                        },
+                        "required": []
+                    }
+                },
+                {
+                    "name": "tool_failure_stats",
+                    "description": "Tools ranked by how many of their calls returned an error, with call counts and failure rates.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "project": { "type": "string", "description": "Only calls in this project" },
+                            "since": { "type": "string", "description": "Only calls since an age (e.g. 7d) or date (e.g. 2026-01-01)" },
+                            "limit": { "type": "integer", "description": "Max tools (default 20)" }
+                        },
+                        "required": []
+                    }
+                },
                 {
                     "name": "create_link",
                     "description": "Link any two entities (task, memory, conversation). Creates a semantic connection with an optional relation label.",
@@ -427,15 +913,291 @@ fn handle_tools_list(id: &Value) -> Value {
     })
 }
 
+// ── Output schemas ───────────────────────────────────────────────────────
+
+fn nullable(ty: &str) -> Value {
+    json!({ "type": [ty, "null"] })
+}
+
+fn object_schema(properties: Value, required: &[&str]) -> Value {
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn memory_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "integer" },
+        "category": { "type": "string" },
+        "key": { "type": "string" },
+        "value": { "type": "string" },
+        "tags": { "type": ["array", "null"], "items": { "type": "string" } },
+        "created_at": { "type": "string" },
+        "updated_at": { "type": "string" },
+        "expires_at": nullable("string")
+    }), &["id", "category", "key", "value", "created_at", "updated_at"])
+}
+
+fn memory_revision_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "integer" },
+        "memory_id": { "type": "integer" },
+        "category": { "type": "string" },
+        "key": { "type": "string" },
+        "rev": { "type": "integer" },
+        "value": { "type": "string" },
+        "tags": { "type": ["array", "null"], "items": { "type": "string" } },
+        "updated_at": { "type": "string" },
+        "replaced_at": { "type": "string" }
+    }), &["id", "memory_id", "category", "key", "rev", "value", "updated_at", "replaced_at"])
+}
+
+fn conversation_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "integer" },
+        "session_id": { "type": "string" },
+        "role": { "type": "string" },
+        "content": { "type": "string" },
+        "project": nullable("string"),
+        "entry_type": nullable("string"),
+        "raw_id": nullable("integer"),
+        "model": nullable("string"),
+        "input_tokens": nullable("integer"),
+        "output_tokens": nullable("integer"),
+        "cache_creation_tokens": nullable("integer"),
+        "cache_read_tokens": nullable("integer"),
+        "message_timestamp": nullable("string"),
+        "message_uuid": nullable("string"),
+        "message_id": nullable("string"),
+        "created_at": { "type": "string" }
+    }), &["id", "session_id", "role", "content", "created_at"])
+}
+
+fn task_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "integer" },
+        "project": { "type": "string" },
+        "subject": { "type": "string" },
+        "description": nullable("string"),
+        "status": { "type": "string", "enum": ["pending", "in_progress", "completed", "blocked", "deleted"] },
+        "priority": nullable("string"),
+        "task_type": nullable("string"),
+        "parent_id": nullable("integer"),
+        "due_date": nullable("string"),
+        "created_by": nullable("string"),
+        "assignee": nullable("string"),
+        "owner": nullable("string"),
+        "session_id": nullable("string"),
+        "created_at": { "type": "string" },
+        "updated_at": { "type": "string" },
+        "recurrence": nullable("string")
+    }), &["id", "project", "subject", "status", "created_at", "updated_at"])
+}
+
+fn task_event_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "integer" },
+        "task_id": { "type": "integer" },
+        "field": { "type": "string" },
+        "old_value": nullable("string"),
+        "new_value": nullable("string"),
+        "session_id": nullable("string"),
+        "actor": nullable("string"),
+        "created_at": { "type": "string" }
+    }), &["id", "task_id", "field", "created_at"])
+}
+
+fn task_comment_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "integer" },
+        "task_id": { "type": "integer" },
+        "parent_id": nullable("integer"),
+        "author": nullable("string"),
+        "body": { "type": "string" },
+        "session_id": nullable("string"),
+        "created_at": { "type": "string" }
+    }), &["id", "task_id", "body", "created_at"])
+}
+
+fn time_entry_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "integer" },
+        "task_id": { "type": "integer" },
+        "started_at": { "type": "string" },
+        "ended_at": nullable("string"),
+        "duration_seconds": nullable("integer"),
+        "session_id": nullable("string"),
+        "actor": nullable("string"),
+        "note": nullable("string"),
+        "created_at": { "type": "string" }
+    }), &["id", "task_id", "started_at", "created_at"])
+}
+
+fn tool_call_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "integer" },
+        "conversation_id": nullable("integer"),
+        "session_id": { "type": "string" },
+        "project": nullable("string"),
+        "tool_use_id": { "type": "string" },
+        "tool_name": { "type": "string" },
+        "input": nullable("string"),
+        "result": nullable("string"),
+        "is_error": { "type": "boolean" },
+        "started_at": nullable("string"),
+        "finished_at": nullable("string"),
+        "duration_ms": nullable("integer"),
+        "created_at": { "type": "string" }
+    }), &["id", "session_id", "tool_use_id", "tool_name", "is_error", "created_at"])
+}
+
+fn usage_row_schema() -> Value {
+    object_schema(json!({
+        "group": { "type": "object", "additionalProperties": nullable("string") },
+        "messages": { "type": "integer" },
+        "input_tokens": { "type": "integer" },
+        "output_tokens": { "type": "integer" },
+        "cache_creation_tokens": { "type": "integer" },
+        "cache_read_tokens": { "type": "integer" },
+        "cache_hit_ratio": nullable("number"),
+        "cost_usd": nullable("number"),
+        "unpriced_models": { "type": "array", "items": { "type": "string" } }
+    }), &["group", "messages", "input_tokens", "output_tokens", "cache_creation_tokens", "cache_read_tokens", "cache_hit_ratio", "cost_usd"])
+}
+
+fn link_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "integer" },
+        "source_type": { "type": "string" },
+        "source_id": { "type": "integer" },
+        "target_type": { "type": "string" },
+        "target_id": { "type": "integer" },
+        "relation": nullable("string"),
+        "created_at": { "type": "string" }
+    }), &["id", "source_type", "source_id", "target_type", "target_id", "created_at"])
+}
+
+/// `{ "count": n, "<key>": [item, ...] }`
+fn list_schema(key: &str, item: Value) -> Value {
+    let mut properties = serde_json::Map::new();
+    properties.insert("count".to_string(), json!({ "type": "integer" }));
+    properties.insert(key.to_string(), json!({ "type": "array", "items": item }));
+    object_schema(Value::Object(properties), &["count", key])
+}
+
+/// JSON Schema of each tool's `structuredContent`.
+fn output_schema(tool: &str) -> Option<Value> {
+    let schema = match tool {
+        "store_memory" => memory_schema(),
+        "search_memories" | "list_memories" => list_schema("memories", memory_schema()),
+        "delete_memory" => object_schema(json!({
+            "deleted": { "type": "boolean" },
+            "category": { "type": "string" },
+            "key": { "type": "string" }
+        }), &["deleted", "category", "key"]),
+        "memory_history" => list_schema("revisions", memory_revision_schema()),
+        "revert_memory" => object_schema(json!({
+            "reverted_to": { "type": "integer" },
+            "memory": memory_schema()
+        }), &["reverted_to", "memory"]),
+        "log_conversation" => conversation_schema(),
+        "search_conversations" | "get_conversation_context" => list_schema("entries", conversation_schema()),
+        "create_task" => task_schema(),
+        "update_task" => {
+            let mut schema = task_schema();
+            schema["properties"]["next_occurrence"] = task_schema();
+            schema["properties"]["unblocked"] = json!({ "type": "array", "items": task_schema() });
+            schema["properties"]["blocked"] = json!({ "type": "array", "items": task_schema() });
+            schema
+        }
+        "get_task" => object_schema(json!({
+            "task": task_schema(),
+            "blocked_by": { "type": "array", "items": task_schema() },
+            "blocks": { "type": "array", "items": task_schema() },
+            "links": { "type": "array", "items": link_schema() },
+            "events": { "type": "array", "items": task_event_schema() },
+            "comments": { "type": "array", "items": task_comment_schema() },
+            "time_entries": { "type": "array", "items": time_entry_schema() }
+        }), &["task", "blocked_by", "blocks", "links", "events", "comments", "time_entries"]),
+        "list_tasks" | "search_tasks" => list_schema("tasks", task_schema()),
+        "delete_task" => object_schema(json!({
+            "deleted": { "type": "boolean" },
+            "task": task_schema()
+        }), &["deleted", "task"]),
+        "add_task_dep" | "remove_task_dep" => {
+            let flag = if tool == "add_task_dep" { "added" } else { "removed" };
+            let mut properties = serde_json::Map::new();
+            properties.insert(flag.to_string(), json!({ "type": "boolean" }));
+            properties.insert("blocker_id".to_string(), json!({ "type": "integer" }));
+            properties.insert("blocked_id".to_string(), json!({ "type": "integer" }));
+            object_schema(Value::Object(properties), &[flag, "blocker_id", "blocked_id"])
+        }
+        "add_task_comment" => task_comment_schema(),
+        "list_task_comments" => list_schema("comments", task_comment_schema()),
+        "start_task_timer" | "log_task_time" => time_entry_schema(),
+        "stop_task_timer" => list_schema("entries", time_entry_schema()),
+        "time_report" => object_schema(json!({
+            "group_by": { "type": "string", "enum": TimeGroup::ALL },
+            "total_seconds": { "type": "integer" },
+            "rows": {
+                "type": "array",
+                "items": object_schema(json!({
+                    "group": nullable("string"),
+                    "entries": { "type": "integer" },
+                    "seconds": { "type": "integer" }
+                }), &["group", "entries", "seconds"])
+            }
+        }), &["group_by", "total_seconds", "rows"]),
+        "critical_path" => object_schema(json!({
+            "project": { "type": "string" },
+            "order": { "type": "array", "items": task_schema() },
+            "ready": { "type": "array", "items": task_schema() },
+            "critical_path": { "type": "array", "items": task_schema() }
+        }), &["project", "order", "ready", "critical_path"]),
+        "token_stats" => object_schema(json!({
+            "group_by": { "type": "array", "items": { "type": "string", "enum": UsageGroup::ALL } },
+            "rows": { "type": "array", "items": usage_row_schema() },
+            "total": usage_row_schema()
+        }), &["group_by", "rows", "total"]),
+        "list_tool_calls" => list_schema("tool_calls", tool_call_schema()),
+        "edited_files" => list_schema("files", object_schema(json!({
+            "path": { "type": "string" },
+            "edits": { "type": "integer" },
+            "failed": { "type": "integer" },
+            "last_edited_at": nullable("string")
+        }), &["path", "edits", "failed"])),
+        "tool_failure_stats" => list_schema("tools", object_schema(json!({
+            "tool_name": { "type": "string" },
+            "calls": { "type": "integer" },
+            "failures": { "type": "integer" },
+            "failure_rate": { "type": "number" }
+        }), &["tool_name", "calls", "failures", "failure_rate"])),
+        "create_link" => link_schema(),
+        "get_links" | "search_linked" => list_schema("links", link_schema()),
+        "delete_link" => object_schema(json!({
+            "deleted": { "type": "boolean" },
+            "link_id": { "type": "integer" }
+        }), &["deleted", "link_id"]),
+        _ => return None,
+    };
+    Some(schema)
+}
+
 fn handle_tools_call(id: &Value, params: &Value, db: &Database) -> Value {
     let tool_name = params.get("name").and_then(|n| n.as_str()).unwrap_or("");
-    let args = params.get("arguments").cloned().unwrap_or(json!({}));
+    let args = match params.get("arguments") {
+        None | Some(Value::Null) => json!({}),
+        Some(a) if a.is_object() => a.clone(),
+        Some(_) => {
+            return respond(id, Err(RpcError::invalid_params("'arguments' must be an object".to_string())))
+        }
+    };
 
     let result = match tool_name {
         "store_memory" => tool_store_memory(&args, db),
         "search_memories" => tool_search_memories(&args, db),
         "list_memories" => tool_list_memories(&args, db),
         "delete_memory" => tool_delete_memory(&args, db),
+        "memory_history" => tool_memory_history(&args, db),
+        "revert_memory" => tool_revert_memory(&args, db),
         "log_conversation" => tool_log_conversation(&args, db),
         "search_conversations" => tool_search_conversations(&args, db),
         "get_conversation_context" => tool_get_conversation_context(&args, db),
@@ -447,23 +1209,42 @@ fn handle_tools_call(id: &Value, params: &Value, db: &Database) -> Value {
         "delete_task" => tool_delete_task(&args, db),
         "add_task_dep" => tool_add_task_dep(&args, db),
         "remove_task_dep" => tool_remove_task_dep(&args, db),
+        "add_task_comment" => tool_add_task_comment(&args, db),
+        "list_task_comments" => tool_list_task_comments(&args, db),
+        "start_task_timer" => tool_start_task_timer(&args, db),
+        "stop_task_timer" => tool_stop_task_timer(&args, db),
+        "log_task_time" => tool_log_task_time(&args, db),
+        "time_report" => tool_time_report(&args, db),
+        "critical_path" => tool_critical_path(&args, db),
+        "token_stats" => tool_token_stats(&args, db),
+        "list_tool_calls" => tool_list_tool_calls(&args, db),
+        "edited_files" => tool_edited_files(&args, db),
+        "tool_failure_stats" => tool_tool_failure_stats(&args, db),
         "create_link" => tool_create_link(&args, db),
         "get_links" => tool_get_links(&args, db),
         "delete_link" => tool_delete_link(&args, db),
         "search_linked" => tool_search_linked(&args, db),
-        _ => Err(format!("Unknown tool: {}", tool_name)),
+        _ => {
+            return respond(id, Err(RpcError {
+                code: INVALID_PARAMS,
+                message: format!("Unknown tool: {}", tool_name),
+                data: Some(json!({ "kind": "unknown_tool", "tool": tool_name })),
+            }))
+        }
     };
 
     match result {
-        Ok(content) => json!({
+        // The text block carries the same JSON for clients without structured output support
+        Ok(structured) => json!({
             "jsonrpc": "2.0",
             "id": id,
             "result": {
-                "content": [{ "type": "text", "text": content }],
+                "content": [{ "type": "text", "text": serde_json::to_string_pretty(&structured).unwrap_or_default() }],
+                "structuredContent": structured,
                 "isError": false
             }
         }),
-        Err(e) => json!({
+        Err(ToolError::Failed(e)) => json!({
             "jsonrpc": "2.0",
             "id": id,
             "result": {
@@ -471,10 +1252,21 @@ fn handle_tools_call(id: &Value, params: &Value, db: &Database) -> Value {
                 "isError": true
             }
         }),
+        Err(ToolError::InvalidParams(message)) => {
+            let data = invalid_params_data(&message);
+            respond(id, Err(RpcError { code: INVALID_PARAMS, message, data: Some(data) }))
+        }
     }
 }
 
-fn tool_store_memory(args: &Value, db: &Database) -> Result<String, String> {
+fn parse_mode(args: &Value) -> Result<SearchMode, String> {
+    match args.get("mode").and_then(|v| v.as_str()) {
+        Some(m) => m.parse::<SearchMode>(),
+        None => Ok(SearchMode::Fts),
+    }
+}
+
+fn tool_store_memory(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let category = args.get("category").and_then(|v| v.as_str())
         .ok_or("missing 'category'")?;
     let key = args.get("key").and_then(|v| v.as_str())
@@ -483,64 +1275,94 @@ fn tool_store_memory(args: &Value, db: &Database) -> Result<String, String> {
         .ok_or("missing 'value'")?;
     let tags: Option<Vec<String>> = args.get("tags")
         .and_then(|v| serde_json::from_value(v.clone()).ok());
+    let expires_at = crate::db::resolve_expiry(
+        args.get("expires_at").and_then(|v| v.as_str()),
+        args.get("ttl").and_then(|v| v.as_str()),
+    )?;
 
-    let mem = db.store_memory(category, key, value, tags.as_deref())
-        .map_err(|e| format!("DB error: {}", e))?;
+    let mem = db.store_memory(category, key, value, tags.as_deref(), expires_at.as_deref())
+        .map_err(|e| db_error("DB error", e))?;
 
-    Ok(serde_json::to_string_pretty(&mem).unwrap_or_default())
+    Ok(json!(mem))
 }
 
-fn tool_search_memories(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_search_memories(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let query = args.get("query").and_then(|v| v.as_str())
         .ok_or("missing 'query'")?;
     let category = args.get("category").and_then(|v| v.as_str());
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    let mode = parse_mode(args)?;
+    let include_expired = args.get("include_expired").and_then(|v| v.as_bool()).unwrap_or(false);
 
-    let memories = db.search_memories(query, category, limit)
-        .map_err(|e| format!("Search error: {}", e))?;
+    let memories = db.semantic_search_memories(query, category, limit, mode, include_expired)
+        .map_err(|e| db_error("Search error", e))?;
 
-    if memories.is_empty() {
-        Ok("No memories found matching the query.".to_string())
-    } else {
-        Ok(format!("Found {} memories:\n{}",
-            memories.len(),
-            serde_json::to_string_pretty(&memories).unwrap_or_default()))
-    }
+    Ok(json!({
+        "count": memories.len(),
+        "memories": memories,
+    }))
 }
 
-fn tool_list_memories(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_list_memories(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let category = args.get("category").and_then(|v| v.as_str());
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
+    let include_expired = args.get("include_expired").and_then(|v| v.as_bool()).unwrap_or(false);
 
-    let memories = db.list_memories(category, limit)
-        .map_err(|e| format!("List error: {}", e))?;
+    let memories = db.list_memories(category, limit, include_expired)
+        .map_err(|e| db_error("List error", e))?;
 
-    if memories.is_empty() {
-        Ok("No memories found.".to_string())
-    } else {
-        Ok(format!("Found {} memories:\n{}",
-            memories.len(),
-            serde_json::to_string_pretty(&memories).unwrap_or_default()))
-    }
+    Ok(json!({
+        "count": memories.len(),
+        "memories": memories,
+    }))
 }
 
-fn tool_delete_memory(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_delete_memory(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let category = args.get("category").and_then(|v| v.as_str())
         .ok_or("missing 'category'")?;
     let key = args.get("key").and_then(|v| v.as_str())
         .ok_or("missing 'key'")?;
 
     let deleted = db.delete_memory(category, key)
-        .map_err(|e| format!("Delete error: {}", e))?;
+        .map_err(|e| db_error("Delete error", e))?;
 
     if deleted {
-        Ok(format!("Deleted memory: {}:{}", category, key))
+        Ok(json!({ "deleted": true, "category": category, "key": key }))
     } else {
-        Err(format!("Memory not found: {}:{}", category, key))
+        Err(ToolError::Failed(format!("Memory not found: {}:{}", category, key)))
     }
 }
 
-fn tool_log_conversation(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_memory_history(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let category = args.get("category").and_then(|v| v.as_str())
+        .ok_or("missing 'category'")?;
+    let key = args.get("key").and_then(|v| v.as_str())
+        .ok_or("missing 'key'")?;
+
+    let revisions = db.memory_history(category, key)
+        .map_err(|e| db_error("History error", e))?;
+
+    Ok(json!({
+        "count": revisions.len(),
+        "revisions": revisions,
+    }))
+}
+
+fn tool_revert_memory(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let category = args.get("category").and_then(|v| v.as_str())
+        .ok_or("missing 'category'")?;
+    let key = args.get("key").and_then(|v| v.as_str())
+        .ok_or("missing 'key'")?;
+    let rev = args.get("rev").and_then(|v| v.as_i64())
+        .ok_or("missing 'rev'")?;
+
+    let mem = db.revert_memory(category, key, rev)
+        .map_err(|e| db_error("Revert error", e))?;
+
+    Ok(json!({ "reverted_to": rev, "memory": mem }))
+}
+
+fn tool_log_conversation(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let session_id = args.get("session_id").and_then(|v| v.as_str())
         .ok_or("missing 'session_id'")?;
     let role = args.get("role").and_then(|v| v.as_str())
@@ -552,52 +1374,47 @@ fn tool_log_conversation(args: &Value, db: &Database) -> Result<String, String>
     let raw_id = args.get("raw_id").and_then(|v| v.as_i64());
 
     if let Some(et) = entry_type {
-        et.parse::<EntryType>().map_err(|e| e)?;
+        et.parse::<EntryType>()?;
     }
 
     let entry = db.log_conversation(session_id, role, content, project, entry_type, raw_id)
-        .map_err(|e| format!("Log error: {}", e))?;
+        .map_err(|e| db_error("Log error", e))?;
 
-    Ok(serde_json::to_string_pretty(&entry).unwrap_or_default())
+    Ok(json!(entry))
 }
 
-fn tool_search_conversations(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_search_conversations(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let query = args.get("query").and_then(|v| v.as_str())
         .ok_or("missing 'query'")?;
     let session_id = args.get("session_id").and_then(|v| v.as_str());
     let entry_type = args.get("entry_type").and_then(|v| v.as_str());
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    let mode = parse_mode(args)?;
 
-    let entries = db.search_conversations(query, session_id, entry_type, limit)
-        .map_err(|e| format!("Search error: {}", e))?;
+    let entries = db.semantic_search_conversations(query, session_id, entry_type, limit, mode)
+        .map_err(|e| db_error("Search error", e))?;
 
-    if entries.is_empty() {
-        Ok("No conversations found matching the query.".to_string())
-    } else {
-        Ok(format!("Found {} conversation entries:\n{}",
-            entries.len(),
-            serde_json::to_string_pretty(&entries).unwrap_or_default()))
-    }
+    Ok(json!({
+        "count": entries.len(),
+        "entries": entries,
+    }))
 }
 
-fn tool_get_conversation_context(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_get_conversation_context(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let session_id = args.get("session_id").and_then(|v| v.as_str())
         .ok_or("missing 'session_id'")?;
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
 
     let entries = db.get_conversation_context(session_id, limit)
-        .map_err(|e| format!("Context error: {}", e))?;
+        .map_err(|e| db_error("Context error", e))?;
 
-    if entries.is_empty() {
-        Ok("No conversation context found for this session.".to_string())
-    } else {
-        Ok(format!("Session context ({} summaries):\n{}",
-            entries.len(),
-            serde_json::to_string_pretty(&entries).unwrap_or_default()))
-    }
+    Ok(json!({
+        "count": entries.len(),
+        "entries": entries,
+    }))
 }
 
-fn tool_create_task(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_create_task(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let project = args.get("project").and_then(|v| v.as_str())
         .ok_or("missing 'project'")?;
     let subject = args.get("subject").and_then(|v| v.as_str())
@@ -611,152 +1428,364 @@ fn tool_create_task(args: &Value, db: &Database) -> Result<String, String> {
     let assignee = args.get("assignee").and_then(|v| v.as_str());
     let owner = args.get("owner").and_then(|v| v.as_str());
     let session_id = args.get("session_id").and_then(|v| v.as_str());
+    let recurrence = args.get("recurrence").and_then(|v| v.as_str());
 
     if let Some(p) = priority {
-        p.parse::<TaskPriority>().map_err(|e| e)?;
+        p.parse::<TaskPriority>()?;
     }
     if let Some(tt) = task_type {
-        tt.parse::<TaskType>().map_err(|e| e)?;
+        tt.parse::<TaskType>()?;
     }
 
     let task = db.create_task(project, subject, description, priority, task_type,
-        parent_id, due_date, created_by, assignee, owner, session_id)
-        .map_err(|e| format!("DB error: {}", e))?;
+        parent_id, due_date, created_by, assignee, owner, session_id, recurrence)
+        .map_err(|e| db_error("DB error", e))?;
 
-    Ok(serde_json::to_string_pretty(&task).unwrap_or_default())
+    Ok(json!(task))
 }
 
-fn tool_update_task(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_update_task(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let task_id = args.get("task_id").and_then(|v| v.as_i64())
         .ok_or("missing 'task_id'")?;
 
     if let Some(s) = args.get("status").and_then(|v| v.as_str()) {
-        s.parse::<TaskStatus>().map_err(|e| e)?;
+        s.parse::<TaskStatus>()?;
     }
     if let Some(p) = args.get("priority").and_then(|v| v.as_str()) {
-        p.parse::<TaskPriority>().map_err(|e| e)?;
+        p.parse::<TaskPriority>()?;
     }
     if let Some(tt) = args.get("task_type").and_then(|v| v.as_str()) {
-        tt.parse::<TaskType>().map_err(|e| e)?;
+        tt.parse::<TaskType>()?;
     }
 
+    let before = dependent_statuses(db, task_id)?;
     let task = db.update_task(task_id, args)
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| db_error("DB error", e))?;
+
+    let mut result = json!(task);
+    let (unblocked, blocked): (Vec<Task>, Vec<Task>) = db.get_task_deps(task_id)
+        .map_err(|e| db_error("DB error", e))?
+        .1
+        .into_iter()
+        .filter(|t| before.get(&t.id).is_some_and(|s| *s != t.status))
+        .partition(|t| t.status == "pending");
+    if !unblocked.is_empty() {
+        result["unblocked"] = json!(unblocked);
+    }
+    if !blocked.is_empty() {
+        result["blocked"] = json!(blocked);
+    }
+    if task.status == "completed" {
+        if let Some(next) = db.next_occurrence(task_id).map_err(|e| db_error("DB error", e))? {
+            result["next_occurrence"] = json!(next);
+        }
+    }
+    Ok(result)
+}
 
-    Ok(serde_json::to_string_pretty(&task).unwrap_or_default())
+/// Status of each task blocked by `task_id`, to report what an update changed.
+fn dependent_statuses(db: &Database, task_id: i64) -> Result<HashMap<i64, String>, ToolError> {
+    let (_, dependents) = db.get_task_deps(task_id)
+        .map_err(|e| db_error("DB error", e))?;
+    Ok(dependents.into_iter().map(|t| (t.id, t.status)).collect())
 }
 
-fn tool_get_task(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_get_task(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let task_id = args.get("task_id").and_then(|v| v.as_i64())
         .ok_or("missing 'task_id'")?;
 
-    let task = db.get_task(task_id)
-        .map_err(|e| format!("DB error: {}", e))?;
-    let (blockers, blocked) = db.get_task_deps(task_id)
-        .map_err(|e| format!("DB error: {}", e))?;
-    let links = db.get_links("task", task_id)
-        .map_err(|e| format!("DB error: {}", e))?;
+    let result = task_detail(db, task_id)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(result)
+}
 
-    let result = serde_json::json!({
+/// A task with its dependencies and links, as returned by get_task
+/// and the `task://{id}` resource.
+pub(crate) fn task_detail(db: &Database, task_id: i64) -> rusqlite::Result<Value> {
+    let task = db.get_task(task_id)?;
+    let (blockers, blocked) = db.get_task_deps(task_id)?;
+    let links = db.get_links("task", task_id)?;
+    let events = db.task_events(task_id)?;
+    let comments = db.list_task_comments(task_id)?;
+    let time_entries = db.task_time_entries(task_id)?;
+
+    Ok(json!({
         "task": task,
         "blocked_by": blockers,
         "blocks": blocked,
         "links": links,
-    });
-
-    Ok(serde_json::to_string_pretty(&result).unwrap_or_default())
+        "events": events,
+        "comments": comments,
+        "time_entries": time_entries,
+    }))
 }
 
-fn tool_list_tasks(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_list_tasks(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let project = args.get("project").and_then(|v| v.as_str());
     let status = args.get("status").and_then(|v| v.as_str());
     let assignee = args.get("assignee").and_then(|v| v.as_str());
     let task_type = args.get("task_type").and_then(|v| v.as_str());
     let priority = args.get("priority").and_then(|v| v.as_str());
+    let ready = args.get("ready").and_then(|v| v.as_bool()).unwrap_or(false);
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
 
     if let Some(s) = status {
-        s.parse::<TaskStatus>().map_err(|e| e)?;
+        s.parse::<TaskStatus>()?;
     }
     if let Some(tt) = task_type {
-        tt.parse::<TaskType>().map_err(|e| e)?;
+        tt.parse::<TaskType>()?;
     }
     if let Some(p) = priority {
-        p.parse::<TaskPriority>().map_err(|e| e)?;
+        p.parse::<TaskPriority>()?;
     }
 
-    let tasks = db.list_tasks(project, status, assignee, task_type, priority, limit)
-        .map_err(|e| format!("List error: {}", e))?;
+    let tasks = db.list_tasks(project, status, assignee, task_type, priority, ready, limit)
+        .map_err(|e| db_error("List error", e))?;
 
-    if tasks.is_empty() {
-        Ok("No tasks found.".to_string())
-    } else {
-        Ok(format!("Found {} tasks:\n{}",
-            tasks.len(),
-            serde_json::to_string_pretty(&tasks).unwrap_or_default()))
-    }
+    Ok(json!({
+        "count": tasks.len(),
+        "tasks": tasks,
+    }))
 }
 
-fn tool_search_tasks(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_search_tasks(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let query = args.get("query").and_then(|v| v.as_str())
         .ok_or("missing 'query'")?;
     let project = args.get("project").and_then(|v| v.as_str());
     let status = args.get("status").and_then(|v| v.as_str());
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+    let mode = parse_mode(args)?;
 
-    let tasks = db.search_tasks(query, project, status, limit)
-        .map_err(|e| format!("Search error: {}", e))?;
+    let tasks = db.semantic_search_tasks(query, project, status, limit, mode)
+        .map_err(|e| db_error("Search error", e))?;
 
-    if tasks.is_empty() {
-        Ok("No tasks found matching the query.".to_string())
-    } else {
-        Ok(format!("Found {} tasks:\n{}",
-            tasks.len(),
-            serde_json::to_string_pretty(&tasks).unwrap_or_default()))
-    }
+    Ok(json!({
+        "count": tasks.len(),
+        "tasks": tasks,
+    }))
 }
 
-fn tool_delete_task(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_delete_task(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let task_id = args.get("task_id").and_then(|v| v.as_i64())
         .ok_or("missing 'task_id'")?;
 
     let updates = serde_json::json!({"status": "deleted"});
     let task = db.update_task(task_id, &updates)
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| db_error("DB error", e))?;
 
-    Ok(format!("Task {} deleted.\n{}", task_id,
-        serde_json::to_string_pretty(&task).unwrap_or_default()))
+    Ok(json!({ "deleted": true, "task": task }))
 }
 
-fn tool_add_task_dep(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_add_task_dep(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let blocker_id = args.get("blocker_id").and_then(|v| v.as_i64())
         .ok_or("missing 'blocker_id'")?;
     let blocked_id = args.get("blocked_id").and_then(|v| v.as_i64())
         .ok_or("missing 'blocked_id'")?;
 
     db.add_task_dep(blocker_id, blocked_id)
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!({ "added": true, "blocker_id": blocker_id, "blocked_id": blocked_id }))
+}
+
+fn tool_add_task_comment(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let task_id = args.get("task_id").and_then(|v| v.as_i64())
+        .ok_or("missing 'task_id'")?;
+    let body = args.get("body").and_then(|v| v.as_str())
+        .ok_or("missing 'body'")?;
+    let author = args.get("author").and_then(|v| v.as_str());
+    let reply_to = args.get("reply_to").and_then(|v| v.as_i64());
+    let session_id = args.get("session_id").and_then(|v| v.as_str());
+
+    let comment = db.add_task_comment(task_id, body, author, reply_to, session_id)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!(comment))
+}
+
+fn tool_list_task_comments(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let task_id = args.get("task_id").and_then(|v| v.as_i64());
+    let query = args.get("query").and_then(|v| v.as_str());
+    let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+
+    let comments = match (query, task_id) {
+        (Some(q), _) => db.search_task_comments(q, task_id, limit),
+        (None, Some(id)) => db.list_task_comments(id),
+        (None, None) => return Err("missing 'task_id' or 'query'".into()),
+    }
+    .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!({
+        "count": comments.len(),
+        "comments": comments,
+    }))
+}
+
+fn tool_start_task_timer(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let task_id = args.get("task_id").and_then(|v| v.as_i64())
+        .ok_or("missing 'task_id'")?;
+    let session_id = args.get("session_id").and_then(|v| v.as_str());
+    let actor = args.get("actor").and_then(|v| v.as_str());
+    let note = args.get("note").and_then(|v| v.as_str());
+
+    let entry = db.start_task_timer(task_id, session_id, actor, note)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!(entry))
+}
+
+fn tool_stop_task_timer(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let task_id = args.get("task_id").and_then(|v| v.as_i64())
+        .ok_or("missing 'task_id'")?;
+    let session_id = args.get("session_id").and_then(|v| v.as_str());
+
+    let entries = db.stop_task_timer(task_id, session_id)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!({
+        "count": entries.len(),
+        "entries": entries,
+    }))
+}
+
+fn tool_log_task_time(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let task_id = args.get("task_id").and_then(|v| v.as_i64())
+        .ok_or("missing 'task_id'")?;
+    let duration = args.get("duration").and_then(|v| v.as_str())
+        .ok_or("missing 'duration'")?;
+    let seconds = parse_duration(duration)?;
+    let started_at = args.get("started_at").and_then(|v| v.as_str());
+    let session_id = args.get("session_id").and_then(|v| v.as_str());
+    let actor = args.get("actor").and_then(|v| v.as_str());
+    let note = args.get("note").and_then(|v| v.as_str());
+
+    let entry = db.log_task_time(task_id, seconds, started_at, session_id, actor, note)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!(entry))
+}
+
+fn tool_time_report(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let group = args.get("group_by").and_then(|v| v.as_str()).unwrap_or("project")
+        .parse::<TimeGroup>()?;
+    let project = args.get("project").and_then(|v| v.as_str());
+    let since = args.get("since").and_then(|v| v.as_str());
+
+    let rows = db.time_report(group, project, since)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!({
+        "group_by": group.to_string(),
+        "total_seconds": rows.iter().map(|r| r.seconds).sum::<i64>(),
+        "rows": rows,
+    }))
+}
+
+fn tool_token_stats(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let groups = match args.get("group_by") {
+        None | Some(Value::Null) => vec![UsageGroup::Project],
+        Some(Value::Array(items)) => items.iter()
+            .map(|g| g.as_str().ok_or("'group_by' items must be strings")?.parse::<UsageGroup>().map_err(ToolError::from))
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(Value::String(s)) => UsageGroup::parse_list(s)?,
+        Some(_) => return Err("'group_by' must be an array of strings".into()),
+    };
+    let since = args.get("since").and_then(|v| v.as_str())
+        .map(crate::db::resolve_since).transpose()?;
+    let prices = match args.get("prices") {
+        None | Some(Value::Null) => PriceTable::from_env().map_err(ToolError::Failed)?,
+        Some(p) => Some(serde_json::from_value::<PriceTable>(p.clone())
+            .map_err(|e| format!("Invalid 'prices': {}", e))?),
+    };
+    let filter = UsageFilter {
+        project: args.get("project").and_then(|v| v.as_str()).map(String::from),
+        session_id: args.get("session_id").and_then(|v| v.as_str()).map(String::from),
+        model: args.get("model").and_then(|v| v.as_str()).map(String::from),
+        since,
+    };
+
+    let records = db.token_usage(&groups, &filter)
+        .map_err(|e| db_error("DB error", e))?;
 
-    Ok(format!("Dependency added: task {} blocks task {}", blocker_id, blocked_id))
+    Ok(json!(usage::build_report(&groups, &records, prices.as_ref())))
 }
 
-fn tool_remove_task_dep(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_list_tool_calls(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let since = args.get("since").and_then(|v| v.as_str())
+        .map(crate::db::resolve_since).transpose()?;
+    let filter = ToolCallFilter {
+        session_id: args.get("session_id").and_then(|v| v.as_str()).map(String::from),
+        project: args.get("project").and_then(|v| v.as_str()).map(String::from),
+        tool_name: args.get("tool_name").and_then(|v| v.as_str()).map(String::from),
+        errors_only: args.get("errors_only").and_then(|v| v.as_bool()).unwrap_or(false),
+        since,
+    };
+    let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+
+    let calls = db.list_tool_calls(&filter, limit)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!({
+        "count": calls.len(),
+        "tool_calls": calls,
+    }))
+}
+
+fn tool_edited_files(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let session_id = args.get("session_id").and_then(|v| v.as_str());
+    let project = args.get("project").and_then(|v| v.as_str());
+
+    let files = db.edited_files(session_id, project)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!({
+        "count": files.len(),
+        "files": files,
+    }))
+}
+
+fn tool_tool_failure_stats(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let project = args.get("project").and_then(|v| v.as_str());
+    let since = args.get("since").and_then(|v| v.as_str());
+    let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+
+    let stats = db.tool_failure_stats(project, since, limit)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!({
+        "count": stats.len(),
+        "tools": stats,
+    }))
+}
+
+fn tool_critical_path(args: &Value, db: &Database) -> Result<Value, ToolError> {
+    let project = args.get("project").and_then(|v| v.as_str())
+        .ok_or("missing 'project'")?;
+
+    let plan = db.task_plan(project)
+        .map_err(|e| db_error("DB error", e))?;
+
+    Ok(json!(plan))
+}
+
+fn tool_remove_task_dep(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let blocker_id = args.get("blocker_id").and_then(|v| v.as_i64())
         .ok_or("missing 'blocker_id'")?;
     let blocked_id = args.get("blocked_id").and_then(|v| v.as_i64())
         .ok_or("missing 'blocked_id'")?;
 
     let removed = db.remove_task_dep(blocker_id, blocked_id)
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| db_error("DB error", e))?;
 
     if removed {
-        Ok(format!("Dependency removed: task {} no longer blocks task {}", blocker_id, blocked_id))
+        Ok(json!({ "removed": true, "blocker_id": blocker_id, "blocked_id": blocked_id }))
     } else {
-        Err(format!("Dependency not found: {} -> {}", blocker_id, blocked_id))
+        Err(ToolError::Failed(format!("Dependency not found: {} -> {}", blocker_id, blocked_id)))
     }
 }
 
-fn tool_create_link(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_create_link(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let source_type = args.get("source_type").and_then(|v| v.as_str())
         .ok_or("missing 'source_type'")?;
     let source_id = args.get("source_id").and_then(|v| v.as_i64())
@@ -768,44 +1797,41 @@ fn tool_create_link(args: &Value, db: &Database) -> Result<String, String> {
     let relation = args.get("relation").and_then(|v| v.as_str());
 
     let link = db.create_link(source_type, source_id, target_type, target_id, relation)
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| db_error("DB error", e))?;
 
-    Ok(serde_json::to_string_pretty(&link).unwrap_or_default())
+    Ok(json!(link))
 }
 
-fn tool_get_links(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_get_links(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let entity_type = args.get("entity_type").and_then(|v| v.as_str())
         .ok_or("missing 'entity_type'")?;
     let entity_id = args.get("entity_id").and_then(|v| v.as_i64())
         .ok_or("missing 'entity_id'")?;
 
     let links = db.get_links(entity_type, entity_id)
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| db_error("DB error", e))?;
 
-    if links.is_empty() {
-        Ok("No links found for this entity.".to_string())
-    } else {
-        Ok(format!("Found {} links:\n{}",
-            links.len(),
-            serde_json::to_string_pretty(&links).unwrap_or_default()))
-    }
+    Ok(json!({
+        "count": links.len(),
+        "links": links,
+    }))
 }
 
-fn tool_delete_link(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_delete_link(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let link_id = args.get("link_id").and_then(|v| v.as_i64())
         .ok_or("missing 'link_id'")?;
 
     let deleted = db.delete_link(link_id)
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| db_error("DB error", e))?;
 
     if deleted {
-        Ok(format!("Link {} deleted.", link_id))
+        Ok(json!({ "deleted": true, "link_id": link_id }))
     } else {
-        Err(format!("Link not found: {}", link_id))
+        Err(ToolError::Failed(format!("Link not found: {}", link_id)))
     }
 }
 
-fn tool_search_linked(args: &Value, db: &Database) -> Result<String, String> {
+fn tool_search_linked(args: &Value, db: &Database) -> Result<Value, ToolError> {
     let entity_type = args.get("entity_type").and_then(|v| v.as_str())
         .ok_or("missing 'entity_type'")?;
     let entity_id = args.get("entity_id").and_then(|v| v.as_i64())
@@ -813,13 +1839,177 @@ fn tool_search_linked(args: &Value, db: &Database) -> Result<String, String> {
     let target_type = args.get("target_type").and_then(|v| v.as_str());
 
     let links = db.search_linked(entity_type, entity_id, target_type)
-        .map_err(|e| format!("Search error: {}", e))?;
+        .map_err(|e| db_error("Search error", e))?;
 
-    if links.is_empty() {
-        Ok("No linked entities found.".to_string())
-    } else {
-        Ok(format!("Found {} linked entities:\n{}",
-            links.len(),
-            serde_json::to_string_pretty(&links).unwrap_or_default()))
+    Ok(json!({
+        "count": links.len(),
+        "links": links,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal JSON Schema check covering what output_schema uses:
+    /// type (single or list), properties, required, items, enum.
+    fn check(schema: &Value, value: &Value, path: &str) {
+        let type_ok = |ty: &str| match ty {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => false,
+        };
+        match &schema["type"] {
+            Value::String(t) => assert!(type_ok(t), "{}: expected {}, got {}", path, t, value),
+            Value::Array(ts) => assert!(
+                ts.iter().any(|t| type_ok(t.as_str().unwrap())),
+                "{}: expected one of {:?}, got {}", path, ts, value
+            ),
+            _ => {}
+        }
+        if let Some(allowed) = schema["enum"].as_array() {
+            assert!(allowed.contains(value), "{}: {} not in enum", path, value);
+        }
+        if let Some(obj) = value.as_object() {
+            for req in schema["required"].as_array().into_iter().flatten() {
+                assert!(obj.contains_key(req.as_str().unwrap()), "{}: missing {}", path, req);
+            }
+            if let Some(props) = schema["properties"].as_object() {
+                for (k, v) in obj {
+                    let sub = props.get(k).unwrap_or_else(|| panic!("{}: undeclared field {}", path, k));
+                    check(sub, v, &format!("{}.{}", path, k));
+                }
+            }
+        }
+        if let Some(items) = value.as_array() {
+            for (i, item) in items.iter().enumerate() {
+                check(&schema["items"], item, &format!("{}[{}]", path, i));
+            }
+        }
+    }
+
+    fn call(db: &Database, name: &str, args: Value) -> Value {
+        let resp = handle_tools_call(&json!(1), &json!({ "name": name, "arguments": args }), db);
+        assert_eq!(resp["result"]["isError"], false, "{}: {}", name, resp);
+        let structured = resp["result"]["structuredContent"].clone();
+        let text: Value = serde_json::from_str(resp["result"]["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(text, structured, "{}: text block must mirror structuredContent", name);
+        check(&output_schema(name).unwrap(), &structured, name);
+        structured
+    }
+
+    #[test]
+    fn test_every_tool_declares_output_schema() {
+        let tools = handle_tools_list(&json!(1));
+        for tool in tools["result"]["tools"].as_array().unwrap() {
+            assert_eq!(tool["outputSchema"]["type"], "object", "{} has no outputSchema", tool["name"]);
+        }
+    }
+
+    #[test]
+    fn test_structured_content_matches_schema() {
+        let db = Database::open_in_memory().unwrap();
+        call(&db, "store_memory", json!({"category": "facts", "key": "k", "value": "v1", "tags": ["a"]}));
+        call(&db, "store_memory", json!({"category": "facts", "key": "k", "value": "v2"}));
+        assert_eq!(call(&db, "search_memories", json!({"query": "v2"}))["count"], 1);
+        call(&db, "list_memories", json!({}));
+        call(&db, "memory_history", json!({"category": "facts", "key": "k"}));
+        call(&db, "revert_memory", json!({"category": "facts", "key": "k", "rev": 1}));
+        call(&db, "delete_memory", json!({"category": "facts", "key": "k"}));
+
+        call(&db, "log_conversation", json!({"session_id": "s", "role": "summary", "content": "did things", "entry_type": "summary"}));
+        call(&db, "search_conversations", json!({"query": "things"}));
+        call(&db, "get_conversation_context", json!({"session_id": "s"}));
+        db.store_pre_compact_batch(&[crate::db::PreCompactMessage {
+            session_id: "s".to_string(),
+            role: "assistant".to_string(),
+            content: "reply".to_string(),
+            project: "p".to_string(),
+            model: Some("claude-sonnet-4-20250514".to_string()),
+            input_tokens: Some(100),
+            output_tokens: Some(50),
+            cache_creation_tokens: None,
+            cache_read_tokens: Some(300),
+            message_timestamp: Some("2026-01-05T10:00:00.000Z".to_string()),
+            message_uuid: None,
+            message_id: None,
+        }]).unwrap();
+        let stats = call(&db, "token_stats", json!({"group_by": ["project", "week"], "prices": {"claude-sonnet": {"input": 3, "output": 15}}}));
+        assert_eq!(stats["rows"][0]["group"]["week"], "2026-01-05");
+        assert_eq!(stats["total"]["cache_hit_ratio"], 0.75);
+        call(&db, "token_stats", json!({}));
+
+        db.store_tool_calls(&[crate::db::ToolUseRecord {
+            session_id: "s".to_string(),
+            project: "p".to_string(),
+            tool_use_id: "toolu_1".to_string(),
+            tool_name: "Edit".to_string(),
+            input: r#"{"file_path": "/r/src/lib.rs"}"#.to_string(),
+            message_uuid: None,
+            timestamp: Some("2026-01-05T10:00:00.000Z".to_string()),
+        }], &[crate::db::ToolResultRecord {
+            tool_use_id: "toolu_1".to_string(),
+            result: "String not found".to_string(),
+            is_error: true,
+            message_uuid: None,
+            timestamp: Some("2026-01-05T10:00:01.500Z".to_string()),
+        }]).unwrap();
+        let calls = call(&db, "list_tool_calls", json!({"session_id": "s", "errors_only": true}));
+        assert_eq!(calls["tool_calls"][0]["duration_ms"], 1500);
+        assert_eq!(call(&db, "edited_files", json!({"session_id": "s"}))["files"][0]["path"], "/r/src/lib.rs");
+        assert_eq!(call(&db, "tool_failure_stats", json!({}))["tools"][0]["failure_rate"], 1.0);
+
+        let a = call(&db, "create_task", json!({"project": "p", "subject": "first", "priority": "high"}));
+        let b = call(&db, "create_task", json!({"project": "p", "subject": "second"}));
+        let (a, b) = (a["id"].as_i64().unwrap(), b["id"].as_i64().unwrap());
+        call(&db, "update_task", json!({"task_id": a, "status": "in_progress"}));
+        call(&db, "add_task_dep", json!({"blocker_id": a, "blocked_id": b}));
+        let link = call(&db, "create_link", json!({"source_type": "task", "source_id": a, "target_type": "task", "target_id": b}));
+        let detail = call(&db, "get_task", json!({"task_id": b}));
+        assert_eq!(detail["blocked_by"][0]["id"], a);
+        call(&db, "list_tasks", json!({"project": "p"}));
+        call(&db, "search_tasks", json!({"query": "second"}));
+        call(&db, "get_links", json!({"entity_type": "task", "entity_id": a}));
+        call(&db, "search_linked", json!({"entity_type": "task", "entity_id": a}));
+        call(&db, "delete_link", json!({"link_id": link["id"]}));
+        let plan = call(&db, "critical_path", json!({"project": "p"}));
+        assert_eq!(plan["critical_path"].as_array().unwrap().len(), 2);
+        assert_eq!(call(&db, "list_tasks", json!({"project": "p", "ready": true}))["count"], 1);
+        let done = call(&db, "update_task", json!({"task_id": a, "status": "completed"}));
+        assert_eq!(done["unblocked"][0]["id"], b);
+        let history = call(&db, "get_task", json!({"task_id": b}))["events"].clone();
+        assert_eq!(history[1]["new_value"], "blocked");
+        assert_eq!(history[2]["actor"], crate::db::DEPENDENCY_ACTOR);
+        let top = call(&db, "add_task_comment", json!({"task_id": b, "body": "needs a schema review", "author": "ana"}));
+        call(&db, "add_task_comment", json!({"task_id": b, "body": "done, looks fine", "reply_to": top["id"]}));
+        assert_eq!(call(&db, "list_task_comments", json!({"task_id": b}))["count"], 2);
+        assert_eq!(call(&db, "list_task_comments", json!({"query": "schema"}))["comments"][0]["id"], top["id"]);
+        assert_eq!(call(&db, "get_task", json!({"task_id": b}))["comments"][1]["parent_id"], top["id"]);
+        call(&db, "start_task_timer", json!({"task_id": a, "session_id": "s"}));
+        call(&db, "log_task_time", json!({"task_id": b, "duration": "1h30m", "actor": "ana"}));
+        assert_eq!(call(&db, "stop_task_timer", json!({"task_id": a, "session_id": "s"}))["count"], 1);
+        assert_eq!(call(&db, "get_task", json!({"task_id": b}))["time_entries"][0]["duration_seconds"], 5400);
+        let report = call(&db, "time_report", json!({"group_by": "source"}));
+        assert_eq!(report["rows"][0]["group"], "human");
+        assert!(report["total_seconds"].as_i64().unwrap() >= 5400);
+        call(&db, "remove_task_dep", json!({"blocker_id": a, "blocked_id": b}));
+        call(&db, "delete_task", json!({"task_id": b}));
+
+        let weekly = call(&db, "create_task", json!({"project": "p", "subject": "audit", "recurrence": "weekly"}));
+        let done = call(&db, "update_task", json!({"task_id": weekly["id"], "status": "completed"}));
+        assert_eq!(done["next_occurrence"]["recurrence"], "FREQ=WEEKLY");
+    }
+
+    #[test]
+    fn test_negotiate_protocol_version() {
+        assert_eq!(negotiate_protocol_version(&json!({"protocolVersion": "2024-11-05"})), "2024-11-05");
+        assert_eq!(negotiate_protocol_version(&json!({"protocolVersion": "2025-06-18"})), "2025-06-18");
+        assert_eq!(negotiate_protocol_version(&json!({"protocolVersion": "1999-01-01"})), SUPPORTED_PROTOCOL_VERSIONS[0]);
+        assert_eq!(negotiate_protocol_version(&json!({})), SUPPORTED_PROTOCOL_VERSIONS[0]);
     }
 }
@@ -0,0 +1,584 @@
+use serde_json::{json, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::mcp;
+use crate::redact::Redactor;
+use crate::resources::{self, Subscriptions};
+
+const ENDPOINT: &str = "/mcp";
+const SESSION_HEADER: &str = "mcp-session-id";
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+const SSE_KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// Per-session state: resource subscriptions and the senders of its open
+/// GET streams. Dropping the session (DELETE) closes the streams.
+#[derive(Default)]
+struct SessionState {
+    streams: Vec<mpsc::Sender<Value>>,
+    subscriptions: Arc<Subscriptions>,
+}
+
+/// Open sessions, keyed by the `Mcp-Session-Id` handed out on initialize.
+#[derive(Default)]
+struct Sessions {
+    inner: Mutex<HashMap<String, SessionState>>,
+    counter: AtomicU64,
+    seed: RandomState,
+}
+
+impl Sessions {
+    fn create(&self) -> (String, Arc<Subscriptions>) {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let mut a = self.seed.build_hasher();
+        a.write_u64(n);
+        a.write_u128(nanos);
+        let mut b = self.seed.build_hasher();
+        b.write_u64(a.finish());
+        b.write_u32(std::process::id());
+        let id = format!("{:016x}{:016x}", a.finish(), b.finish());
+        let state = SessionState::default();
+        let subscriptions = Arc::clone(&state.subscriptions);
+        self.inner.lock().unwrap().insert(id.clone(), state);
+        (id, subscriptions)
+    }
+
+    fn subscriptions(&self, id: &str) -> Option<Arc<Subscriptions>> {
+        self.inner.lock().unwrap().get(id).map(|s| Arc::clone(&s.subscriptions))
+    }
+
+    fn remove(&self, id: &str) -> bool {
+        self.inner.lock().unwrap().remove(id).is_some()
+    }
+
+    fn open_stream(&self, id: &str) -> Option<mpsc::Receiver<Value>> {
+        let mut inner = self.inner.lock().unwrap();
+        let state = inner.get_mut(id)?;
+        let (tx, rx) = mpsc::channel();
+        state.streams.push(tx);
+        Some(rx)
+    }
+
+    /// Send a message on every open stream of a session, dropping closed ones.
+    fn notify(&self, id: &str, message: &Value) {
+        if let Some(state) = self.inner.lock().unwrap().get_mut(id) {
+            state.streams.retain(|tx| tx.send(message.clone()).is_ok());
+        }
+    }
+
+    /// Re-check every session's subscriptions and notify its streams.
+    fn poll_all(&self, db: &Database) {
+        let all: Vec<(String, Arc<Subscriptions>)> = self.inner.lock().unwrap()
+            .iter()
+            .map(|(id, s)| (id.clone(), Arc::clone(&s.subscriptions)))
+            .collect();
+        for (id, subscriptions) in all {
+            for uri in subscriptions.poll(db) {
+                self.notify(&id, &resources::updated_notification(&uri));
+            }
+        }
+    }
+}
+
+/// A parsed HTTP/1.1 request. Header names are lowercased.
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|s| s.as_str())
+    }
+
+    fn accepts(&self, mime: &str) -> bool {
+        self.header("accept")
+            .map(|a| a.split(',').any(|t| {
+                let t = t.split(';').next().unwrap_or("").trim();
+                t == mime || t == "*/*"
+            }))
+            .unwrap_or(false)
+    }
+
+    fn keep_alive(&self) -> bool {
+        !self.header("connection")
+            .map(|c| c.eq_ignore_ascii_case("close"))
+            .unwrap_or(false)
+    }
+}
+
+/// MCP server over the Streamable HTTP transport.
+/// POST /mcp carries JSON-RPC messages, GET /mcp opens an SSE stream for
+/// server-initiated messages, DELETE /mcp ends the session. Each connection
+/// gets its own thread and SQLite connection.
+pub fn serve_http(db_path: &PathBuf, addr: &str) -> Result<(), Box<dyn std::error::Error>> {
+    // Migrate once up front so connection threads don't race on it
+    let db = Database::open(db_path)?;
+    db.enable_wal()?;
+    drop(db);
+
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    if !local.ip().is_loopback() {
+        eprintln!(
+            "leafhill-persistent-memory: warning: listening on non-loopback address {}; \
+             the server has no authentication",
+            local
+        );
+    }
+    eprintln!(
+        "leafhill-persistent-memory: MCP HTTP server listening on http://{}{} (db: {:?})",
+        local, ENDPOINT, db_path
+    );
+
+    let sessions = Arc::new(Sessions::default());
+    let redactor = Config::load_for_current_dir().redactor();
+
+    // Pick up changes made by other connections and processes
+    {
+        let sessions = Arc::clone(&sessions);
+        let db_path = db_path.clone();
+        std::thread::spawn(move || {
+            let db = match Database::open(&db_path) {
+                Ok(db) => db,
+                Err(e) => {
+                    eprintln!("leafhill-persistent-memory: subscription poller disabled: {}", e);
+                    return;
+                }
+            };
+            loop {
+                std::thread::sleep(resources::POLL_INTERVAL);
+                sessions.poll_all(&db);
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("leafhill-persistent-memory: accept failed: {}", e);
+                continue;
+            }
+        };
+        let sessions = Arc::clone(&sessions);
+        let db_path = db_path.clone();
+        let redactor = redactor.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &db_path, &sessions, redactor) {
+                eprintln!("leafhill-persistent-memory: connection error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, db_path: &Path, sessions: &Sessions, redactor: Redactor) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut stream = stream;
+    let mut db: Option<Database> = None;
+
+    loop {
+        let request = match read_request(&mut reader) {
+            Ok(Some(r)) => r,
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                write_response(&mut stream, 400, &[], "text/plain", e.to_string().as_bytes(), false)?;
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let keep_alive = request.keep_alive();
+
+        let path = request.path.split('?').next().unwrap_or("");
+        if path != ENDPOINT {
+            write_response(&mut stream, 404, &[], "text/plain", b"Not Found", keep_alive)?;
+        } else if !origin_allowed(request.header("origin")) {
+            write_response(&mut stream, 403, &[], "text/plain", b"Origin not allowed", keep_alive)?;
+        } else {
+            match request.method.as_str() {
+                "POST" => {
+                    if db.is_none() {
+                        match Database::open(&db_path.to_path_buf()) {
+                            Ok(mut d) => {
+                                d.set_redactor(redactor.clone());
+                                db = Some(d);
+                            }
+                            Err(e) => {
+                                let body = rpc_error(mcp::INTERNAL_ERROR, &format!("Database error: {}", e));
+                                write_json(&mut stream, 500, &[], &body, false)?;
+                                return Ok(());
+                            }
+                        }
+                    }
+                    if !handle_post(&mut stream, &request, db.as_ref().unwrap(), sessions)? {
+                        return Ok(());
+                    }
+                }
+                "GET" => {
+                    // The SSE stream holds the connection until the client goes away
+                    return handle_get(&mut stream, &request, sessions);
+                }
+                "DELETE" => {
+                    match request.header(SESSION_HEADER) {
+                        Some(id) if sessions.remove(id) => {
+                            write_response(&mut stream, 200, &[], "text/plain", b"", keep_alive)?;
+                        }
+                        Some(_) => {
+                            write_response(&mut stream, 404, &[], "text/plain", b"Unknown session", keep_alive)?;
+                        }
+                        None => {
+                            write_response(&mut stream, 400, &[], "text/plain", b"Missing Mcp-Session-Id", keep_alive)?;
+                        }
+                    }
+                }
+                _ => {
+                    write_response(
+                        &mut stream, 405, &[("Allow", "GET, POST, DELETE".to_string())],
+                        "text/plain", b"Method Not Allowed", keep_alive,
+                    )?;
+                }
+            }
+        }
+
+        if !keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+/// Handle a POST carrying a JSON-RPC message or batch. Returns whether the
+/// connection can be reused.
+fn handle_post(
+    stream: &mut TcpStream,
+    request: &Request,
+    db: &Database,
+    sessions: &Sessions,
+) -> io::Result<bool> {
+    let keep_alive = request.keep_alive();
+    let message: Value = match serde_json::from_slice(&request.body) {
+        Ok(v) => v,
+        Err(e) => {
+            let body = rpc_error(mcp::PARSE_ERROR, &format!("Parse error: {}", e));
+            write_json(stream, 400, &[], &body, keep_alive)?;
+            return Ok(keep_alive);
+        }
+    };
+    let is_initialize_request = |m: &Value| {
+        m.get("method").and_then(|m| m.as_str()) == Some("initialize") && m.get("id").is_some()
+    };
+    let is_initialize = match &message {
+        Value::Array(items) => items.iter().any(is_initialize_request),
+        m => is_initialize_request(m),
+    };
+    let mut headers: Vec<(&str, String)> = Vec::new();
+    let (session_id, subscriptions) = if is_initialize {
+        let (id, subscriptions) = sessions.create();
+        headers.push(("Mcp-Session-Id", id.clone()));
+        (id, subscriptions)
+    } else {
+        match request.header(SESSION_HEADER) {
+            Some(id) => match sessions.subscriptions(id) {
+                Some(subscriptions) => (id.to_string(), subscriptions),
+                None => {
+                    let body = rpc_error(-32001, "Session not found");
+                    write_json(stream, 404, &[], &body, keep_alive)?;
+                    return Ok(keep_alive);
+                }
+            },
+            None => {
+                let body = rpc_error(mcp::INVALID_REQUEST, "Bad Request: missing Mcp-Session-Id header");
+                write_json(stream, 400, &[], &body, keep_alive)?;
+                return Ok(keep_alive);
+            }
+        }
+    };
+
+    let response = mcp::handle_payload(&message, db, &subscriptions);
+
+    // Report changes made by this request on the session's GET streams
+    for uri in subscriptions.poll(db) {
+        sessions.notify(&session_id, &resources::updated_notification(&uri));
+    }
+
+    let response = match response {
+        Some(r) => r,
+        None => {
+            write_response(stream, 202, &headers, "text/plain", b"", keep_alive)?;
+            return Ok(keep_alive);
+        }
+    };
+
+    // Clients must accept both; only answer as SSE when JSON isn't acceptable
+    if request.accepts("text/event-stream") && !request.accepts("application/json") {
+        write_sse_head(stream, &headers)?;
+        write_sse_event(stream, &response)?;
+        return Ok(false);
+    }
+    write_json(stream, 200, &headers, &response, keep_alive)?;
+    Ok(keep_alive)
+}
+
+/// Open an SSE stream for server-initiated messages on an existing session.
+fn handle_get(stream: &mut TcpStream, request: &Request, sessions: &Sessions) -> io::Result<()> {
+    if !request.accepts("text/event-stream") {
+        return write_response(stream, 405, &[("Allow", "POST, DELETE".to_string())], "text/plain", b"", false);
+    }
+    let receiver = match request.header(SESSION_HEADER) {
+        Some(id) => match sessions.open_stream(id) {
+            Some(rx) => rx,
+            None => return write_response(stream, 404, &[], "text/plain", b"Unknown session", false),
+        },
+        None => return write_response(stream, 400, &[], "text/plain", b"Missing Mcp-Session-Id", false),
+    };
+
+    write_sse_head(stream, &[])?;
+    loop {
+        match receiver.recv_timeout(SSE_KEEPALIVE) {
+            Ok(message) => write_sse_event(stream, &message)?,
+            Err(RecvTimeoutError::Timeout) => {
+                stream.write_all(b": keepalive\n\n")?;
+                stream.flush()?;
+            }
+            // Session was deleted
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+/// Reject browser requests from non-local origins (DNS rebinding protection).
+fn origin_allowed(origin: Option<&str>) -> bool {
+    let origin = match origin {
+        Some(o) => o,
+        None => return true,
+    };
+    let host = origin
+        .split("://")
+        .nth(1)
+        .unwrap_or(origin)
+        .split('/')
+        .next()
+        .unwrap_or("");
+    let host = if host.starts_with('[') {
+        host.split(']').next().map(|h| &h[1..]).unwrap_or("")
+    } else {
+        host.split(':').next().unwrap_or("")
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// Read one HTTP/1.1 request. Returns `Ok(None)` on a cleanly closed connection.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(m), Some(p), Some(v)) if v.starts_with("HTTP/1.") => (m.to_string(), p.to_string()),
+        _ => return Err(invalid("malformed request line")),
+    };
+
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("unexpected end of headers"));
+        }
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if trimmed.is_empty() {
+            break;
+        }
+        let (name, value) = trimmed.split_once(':').ok_or_else(|| invalid("malformed header"))?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    if headers.contains_key("transfer-encoding") {
+        return Err(invalid("chunked request bodies are not supported; send Content-Length"));
+    }
+    let length = match headers.get("content-length") {
+        Some(v) => v.parse::<usize>().map_err(|_| invalid("invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(invalid("request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(Request { method, path, headers, body }))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn rpc_error(code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": code, "message": message }
+    })
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: u16,
+    headers: &[(&str, String)],
+    content_type: &str,
+    body: &[u8],
+    keep_alive: bool,
+) -> io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, status_text(status));
+    head.push_str(&format!("Content-Type: {}\r\n", content_type));
+    head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    if !keep_alive {
+        head.push_str("Connection: close\r\n");
+    }
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+fn write_json(
+    stream: &mut TcpStream,
+    status: u16,
+    headers: &[(&str, String)],
+    body: &Value,
+    keep_alive: bool,
+) -> io::Result<()> {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    write_response(stream, status, headers, "application/json", &body, keep_alive)
+}
+
+fn write_sse_head(stream: &mut TcpStream, headers: &[(&str, String)]) -> io::Result<()> {
+    let mut head = String::from(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n",
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.flush()
+}
+
+fn write_sse_event(stream: &mut TcpStream, message: &Value) -> io::Result<()> {
+    let data = serde_json::to_string(message).unwrap_or_default();
+    stream.write_all(format!("event: message\ndata: {}\n\n", data).as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_request_with_body() {
+        let raw = "POST /mcp HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+                   Mcp-Session-Id: abc\r\nContent-Length: 2\r\n\r\n{}";
+        let mut reader = Cursor::new(raw.as_bytes());
+        let req = read_request(&mut reader).unwrap().unwrap();
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/mcp");
+        assert_eq!(req.header("mcp-session-id"), Some("abc"));
+        assert_eq!(req.body, b"{}");
+        assert!(read_request(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_request_rejects_chunked() {
+        let raw = "POST /mcp HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let err = read_request(&mut Cursor::new(raw.as_bytes())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_accepts() {
+        let mut headers = HashMap::new();
+        headers.insert("accept".to_string(), "application/json, text/event-stream;q=0.9".to_string());
+        let req = Request { method: "POST".into(), path: "/mcp".into(), headers, body: vec![] };
+        assert!(req.accepts("application/json"));
+        assert!(req.accepts("text/event-stream"));
+        assert!(!req.accepts("text/html"));
+    }
+
+    #[test]
+    fn test_origin_allowed() {
+        assert!(origin_allowed(None));
+        assert!(origin_allowed(Some("http://localhost:3000")));
+        assert!(origin_allowed(Some("http://127.0.0.1")));
+        assert!(origin_allowed(Some("http://[::1]:8080")));
+        assert!(!origin_allowed(Some("https://evil.example.com")));
+        assert!(!origin_allowed(Some("http://localhost.evil.com")));
+    }
+
+    #[test]
+    fn test_session_lifecycle() {
+        let sessions = Sessions::default();
+        let (a, _) = sessions.create();
+        let (b, _) = sessions.create();
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 32);
+        assert!(sessions.subscriptions(&a).is_some());
+        let rx = sessions.open_stream(&a).unwrap();
+        assert!(sessions.remove(&a));
+        assert!(sessions.subscriptions(&a).is_none());
+        assert!(matches!(rx.recv_timeout(Duration::from_millis(10)), Err(RecvTimeoutError::Disconnected)));
+        assert!(sessions.open_stream(&a).is_none());
+    }
+
+    #[test]
+    fn test_poll_all_notifies_streams() {
+        let db = Database::open_in_memory().unwrap();
+        db.store_memory("facts", "lang", "Rust", None, None).unwrap();
+        let sessions = Sessions::default();
+        let (id, subscriptions) = sessions.create();
+        subscriptions.subscribe(&db, "memory://facts/lang").unwrap();
+        let rx = sessions.open_stream(&id).unwrap();
+
+        sessions.poll_all(&db);
+        assert!(rx.try_recv().is_err());
+
+        db.store_memory("facts", "lang", "Rust 2021", None, None).unwrap();
+        sessions.poll_all(&db);
+        let note = rx.try_recv().unwrap();
+        assert_eq!(note["method"], "notifications/resources/updated");
+        assert_eq!(note["params"]["uri"], "memory://facts/lang");
+    }
+}
@@ -0,0 +1,637 @@
+use rusqlite::{Connection, params};
+
+/// A single numbered schema migration.
+/// Versions are strictly increasing; each migration runs in its own transaction
+/// and is recorded in `schema_migrations` on success.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    up: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: u32,
+    pub name: &'static str,
+    pub applied_at: Option<String>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "initial_schema", up: m0001_initial_schema },
+    Migration { version: 2, name: "conversation_entry_type", up: m0002_conversation_entry_type },
+    Migration { version: 3, name: "pre_compact_metadata", up: m0003_pre_compact_metadata },
+    Migration { version: 4, name: "memory_revisions", up: m0004_memory_revisions },
+    Migration { version: 5, name: "embeddings", up: m0005_embeddings },
+    Migration { version: 6, name: "memory_expiry", up: m0006_memory_expiry },
+    Migration { version: 7, name: "task_recurrence", up: m0007_task_recurrence },
+    Migration { version: 8, name: "task_events", up: m0008_task_events },
+    Migration { version: 9, name: "task_comments", up: m0009_task_comments },
+    Migration { version: 10, name: "task_time_entries", up: m0010_task_time_entries },
+    Migration { version: 11, name: "conversation_message_uuid", up: m0011_conversation_message_uuid },
+    Migration { version: 12, name: "transcript_watermarks", up: m0012_transcript_watermarks },
+    Migration { version: 13, name: "tool_calls", up: m0013_tool_calls },
+    Migration { version: 14, name: "projects", up: m0014_projects },
+    Migration { version: 15, name: "task_blocked_backfill", up: m0015_task_blocked_backfill },
+    Migration { version: 16, name: "conversation_message_id", up: m0016_conversation_message_id },
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+fn ensure_version_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );"
+    )
+}
+
+/// Highest applied migration version (0 for a fresh or pre-migration database).
+pub fn current_version(conn: &Connection) -> rusqlite::Result<u32> {
+    ensure_version_table(conn)?;
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+        [],
+        |row| row.get(0),
+    )
+}
+
+pub fn status(conn: &Connection) -> rusqlite::Result<Vec<MigrationStatus>> {
+    ensure_version_table(conn)?;
+    let mut stmt = conn.prepare("SELECT applied_at FROM schema_migrations WHERE version = ?1")?;
+    let mut out = Vec::new();
+    for m in MIGRATIONS {
+        let applied_at: Option<String> = match stmt.query_row(params![m.version], |row| row.get(0)) {
+            Ok(ts) => Some(ts),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(e),
+        };
+        out.push(MigrationStatus { version: m.version, name: m.name, applied_at });
+    }
+    Ok(out)
+}
+
+/// Apply all pending migrations up to and including `target`.
+/// Returns the versions that were applied, in order.
+pub fn migrate_to(conn: &Connection, target: u32) -> rusqlite::Result<Vec<u32>> {
+    let current = current_version(conn)?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(migration_err(format!(
+            "Database schema version {} is newer than this binary supports ({}). Upgrade leafhill-persistent-memory.",
+            current, latest
+        )));
+    }
+    if target > latest {
+        return Err(migration_err(format!(
+            "Unknown schema version {}. Latest known version is {}.",
+            target, latest
+        )));
+    }
+
+    let mut applied = Vec::new();
+    for m in MIGRATIONS.iter().filter(|m| m.version > current && m.version <= target) {
+        let tx = conn.unchecked_transaction()?;
+        (m.up)(&tx).map_err(|e| with_context(e, m))?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES (?1, ?2)",
+            params![m.version, m.name],
+        )?;
+        tx.commit().map_err(|e| with_context(e, m))?;
+        applied.push(m.version);
+    }
+    Ok(applied)
+}
+
+fn migration_err(msg: String) -> rusqlite::Error {
+    rusqlite::Error::InvalidParameterName(msg)
+}
+
+/// Prefix an error with the migration that produced it, keeping the SQLite error code.
+fn with_context(e: rusqlite::Error, m: &Migration) -> rusqlite::Error {
+    let prefix = format!("migration {} ({}) failed", m.version, m.name);
+    match e {
+        rusqlite::Error::SqliteFailure(code, msg) => rusqlite::Error::SqliteFailure(
+            code,
+            Some(format!("{}: {}", prefix, msg.unwrap_or_else(|| code.to_string()))),
+        ),
+        other => migration_err(format!("{}: {}", prefix, other)),
+    }
+}
+
+/// Add a column unless it already exists.
+/// Databases created before versioned migrations may already have some of these columns.
+fn add_column(conn: &Connection, table: &str, column: &str, decl: &str) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .iter()
+        .any(|c| c == column);
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+    }
+    Ok(())
+}
+
+// ── Migrations ───────────────────────────────────────────────────────
+
+fn m0001_initial_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS memories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            category TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            tags TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            UNIQUE(category, key)
+        );
+
+        CREATE TABLE IF NOT EXISTS conversations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT NOT NULL,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            project TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS memories_fts USING fts5(
+            key, value, tags, content=memories, content_rowid=id
+        );
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS conversations_fts USING fts5(
+            content, content=conversations, content_rowid=id
+        );
+
+        -- Triggers to keep FTS in sync
+        CREATE TRIGGER IF NOT EXISTS memories_ai AFTER INSERT ON memories BEGIN
+            INSERT INTO memories_fts(rowid, key, value, tags)
+            VALUES (new.id, new.key, new.value, new.tags);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS memories_ad AFTER DELETE ON memories BEGIN
+            INSERT INTO memories_fts(memories_fts, rowid, key, value, tags)
+            VALUES ('delete', old.id, old.key, old.value, old.tags);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS memories_au AFTER UPDATE ON memories BEGIN
+            INSERT INTO memories_fts(memories_fts, rowid, key, value, tags)
+            VALUES ('delete', old.id, old.key, old.value, old.tags);
+            INSERT INTO memories_fts(rowid, key, value, tags)
+            VALUES (new.id, new.key, new.value, new.tags);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS conversations_ai AFTER INSERT ON conversations BEGIN
+            INSERT INTO conversations_fts(rowid, content)
+            VALUES (new.id, new.content);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS conversations_ad AFTER DELETE ON conversations BEGIN
+            INSERT INTO conversations_fts(conversations_fts, rowid, content)
+            VALUES ('delete', old.id, old.content);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS conversations_au AFTER UPDATE ON conversations BEGIN
+            INSERT INTO conversations_fts(conversations_fts, rowid, content)
+            VALUES ('delete', old.id, old.content);
+            INSERT INTO conversations_fts(rowid, content)
+            VALUES (new.id, new.content);
+        END;
+
+        CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            description TEXT,
+            status TEXT NOT NULL DEFAULT 'pending',
+            priority TEXT DEFAULT 'medium',
+            task_type TEXT DEFAULT 'claude',
+            parent_id INTEGER REFERENCES tasks(id),
+            due_date TEXT,
+            created_by TEXT,
+            assignee TEXT,
+            owner TEXT,
+            session_id TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        CREATE TABLE IF NOT EXISTS task_deps (
+            blocker_id INTEGER NOT NULL REFERENCES tasks(id),
+            blocked_id INTEGER NOT NULL REFERENCES tasks(id),
+            PRIMARY KEY (blocker_id, blocked_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source_type TEXT NOT NULL,
+            source_id INTEGER NOT NULL,
+            target_type TEXT NOT NULL,
+            target_id INTEGER NOT NULL,
+            relation TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            UNIQUE(source_type, source_id, target_type, target_id)
+        );
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS tasks_fts USING fts5(
+            subject, description, content=tasks, content_rowid=id
+        );
+
+        CREATE TRIGGER IF NOT EXISTS tasks_ai AFTER INSERT ON tasks BEGIN
+            INSERT INTO tasks_fts(rowid, subject, description)
+            VALUES (new.id, new.subject, new.description);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS tasks_ad AFTER DELETE ON tasks BEGIN
+            INSERT INTO tasks_fts(tasks_fts, rowid, subject, description)
+            VALUES ('delete', old.id, old.subject, old.description);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS tasks_au AFTER UPDATE ON tasks BEGIN
+            INSERT INTO tasks_fts(tasks_fts, rowid, subject, description)
+            VALUES ('delete', old.id, old.subject, old.description);
+            INSERT INTO tasks_fts(rowid, subject, description)
+            VALUES (new.id, new.subject, new.description);
+        END;
+        "
+    )
+}
+
+fn m0002_conversation_entry_type(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "conversations", "entry_type", "TEXT")?;
+    add_column(conn, "conversations", "raw_id", "INTEGER")
+}
+
+/// v1.4: metadata columns for PreCompact transcript storage
+fn m0003_pre_compact_metadata(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "conversations", "model", "TEXT")?;
+    add_column(conn, "conversations", "input_tokens", "INTEGER")?;
+    add_column(conn, "conversations", "output_tokens", "INTEGER")?;
+    add_column(conn, "conversations", "cache_creation_tokens", "INTEGER")?;
+    add_column(conn, "conversations", "cache_read_tokens", "INTEGER")?;
+    add_column(conn, "conversations", "message_timestamp", "TEXT")
+}
+
+/// Prior versions of memories, captured by trigger whenever a value/tags change or a row is deleted.
+fn m0004_memory_revisions(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS memory_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            memory_id INTEGER NOT NULL,
+            category TEXT NOT NULL,
+            key TEXT NOT NULL,
+            rev INTEGER NOT NULL,
+            value TEXT NOT NULL,
+            tags TEXT,
+            updated_at TEXT NOT NULL,
+            replaced_at TEXT NOT NULL DEFAULT (datetime('now')),
+            UNIQUE(category, key, rev)
+        );
+
+        CREATE TRIGGER IF NOT EXISTS memories_rev_au AFTER UPDATE OF value, tags ON memories
+        WHEN old.value IS NOT new.value OR old.tags IS NOT new.tags BEGIN
+            INSERT INTO memory_revisions (memory_id, category, key, rev, value, tags, updated_at)
+            VALUES (old.id, old.category, old.key,
+                COALESCE((SELECT MAX(rev) FROM memory_revisions
+                          WHERE category = old.category AND key = old.key), 0) + 1,
+                old.value, old.tags, old.updated_at);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS memories_rev_ad AFTER DELETE ON memories BEGIN
+            INSERT INTO memory_revisions (memory_id, category, key, rev, value, tags, updated_at)
+            VALUES (old.id, old.category, old.key,
+                COALESCE((SELECT MAX(rev) FROM memory_revisions
+                          WHERE category = old.category AND key = old.key), 0) + 1,
+                old.value, old.tags, old.updated_at);
+        END;
+        "
+    )
+}
+
+/// Vectors for semantic search, one per entity, tagged with the embedder model that produced them.
+fn m0005_embeddings(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS embeddings (
+            entity_type TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            model TEXT NOT NULL,
+            dim INTEGER NOT NULL,
+            vector BLOB NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (entity_type, entity_id)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_embeddings_model ON embeddings(entity_type, model);
+
+        CREATE TRIGGER IF NOT EXISTS memories_emb_ad AFTER DELETE ON memories BEGIN
+            DELETE FROM embeddings WHERE entity_type = 'memory' AND entity_id = old.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS conversations_emb_ad AFTER DELETE ON conversations BEGIN
+            DELETE FROM embeddings WHERE entity_type = 'conversation' AND entity_id = old.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS tasks_emb_ad AFTER DELETE ON tasks BEGIN
+            DELETE FROM embeddings WHERE entity_type = 'task' AND entity_id = old.id;
+        END;
+        "
+    )
+}
+
+fn m0006_memory_expiry(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "memories", "expires_at", "TEXT")?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_memories_expires_at ON memories(expires_at)
+             WHERE expires_at IS NOT NULL;"
+    )
+}
+
+fn m0007_task_recurrence(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "tasks", "recurrence", "TEXT")
+}
+
+fn m0008_task_events(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS task_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id INTEGER NOT NULL,
+            field TEXT NOT NULL,
+            old_value TEXT,
+            new_value TEXT,
+            session_id TEXT,
+            actor TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_task_events_task_id ON task_events(task_id);"
+    )
+}
+
+fn m0009_task_comments(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS task_comments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id INTEGER NOT NULL,
+            parent_id INTEGER,
+            author TEXT,
+            body TEXT NOT NULL,
+            session_id TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_task_comments_task_id ON task_comments(task_id);
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS task_comments_fts USING fts5(
+            body, author, content=task_comments, content_rowid=id
+        );
+
+        CREATE TRIGGER IF NOT EXISTS task_comments_ai AFTER INSERT ON task_comments BEGIN
+            INSERT INTO task_comments_fts(rowid, body, author)
+            VALUES (new.id, new.body, new.author);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS task_comments_ad AFTER DELETE ON task_comments BEGIN
+            INSERT INTO task_comments_fts(task_comments_fts, rowid, body, author)
+            VALUES ('delete', old.id, old.body, old.author);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS task_comments_au AFTER UPDATE ON task_comments BEGIN
+            INSERT INTO task_comments_fts(task_comments_fts, rowid, body, author)
+            VALUES ('delete', old.id, old.body, old.author);
+            INSERT INTO task_comments_fts(rowid, body, author)
+            VALUES (new.id, new.body, new.author);
+        END;"
+    )
+}
+
+fn m0010_task_time_entries(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS task_time_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id INTEGER NOT NULL,
+            started_at TEXT NOT NULL,
+            ended_at TEXT,
+            duration_seconds INTEGER,
+            session_id TEXT,
+            actor TEXT,
+            note TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_task_time_entries_task_id ON task_time_entries(task_id);
+        CREATE INDEX IF NOT EXISTS idx_task_time_entries_running
+            ON task_time_entries(task_id) WHERE ended_at IS NULL;"
+    )
+}
+
+fn m0011_conversation_message_uuid(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "conversations", "message_uuid", "TEXT")?;
+    conn.execute_batch(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_conversations_message_uuid
+            ON conversations(message_uuid) WHERE message_uuid IS NOT NULL;
+        CREATE INDEX IF NOT EXISTS idx_conversations_session_timestamp
+            ON conversations(session_id, message_timestamp);"
+    )
+}
+
+fn m0012_transcript_watermarks(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS transcript_watermarks (
+            transcript_path TEXT PRIMARY KEY,
+            session_id TEXT,
+            byte_offset INTEGER NOT NULL,
+            last_uuid TEXT,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );"
+    )
+}
+
+fn m0013_tool_calls(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tool_calls (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            conversation_id INTEGER,
+            session_id TEXT NOT NULL,
+            project TEXT,
+            tool_use_id TEXT NOT NULL UNIQUE,
+            tool_name TEXT NOT NULL,
+            input TEXT,
+            result TEXT,
+            is_error INTEGER NOT NULL DEFAULT 0,
+            started_at TEXT,
+            finished_at TEXT,
+            duration_ms INTEGER,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_tool_calls_session_id ON tool_calls(session_id);
+        CREATE INDEX IF NOT EXISTS idx_tool_calls_tool_name ON tool_calls(tool_name);
+        CREATE INDEX IF NOT EXISTS idx_tool_calls_conversation_id ON tool_calls(conversation_id);
+
+        CREATE TRIGGER IF NOT EXISTS conversations_tool_calls_ad AFTER DELETE ON conversations BEGIN
+            UPDATE tool_calls SET conversation_id = NULL WHERE conversation_id = old.id;
+        END;"
+    )
+}
+
+/// Project registry. `project_keys` maps each alias, git remote and git root
+/// (`kind`) to the project it resolves to.
+fn m0014_projects(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS projects (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        CREATE TABLE IF NOT EXISTS project_keys (
+            kind TEXT NOT NULL,
+            value TEXT NOT NULL,
+            project_id INTEGER NOT NULL REFERENCES projects(id),
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (kind, value)
+        );
+        CREATE INDEX IF NOT EXISTS idx_project_keys_project_id ON project_keys(project_id);"
+    )
+}
+
+/// Mark pending tasks that already have open blockers as blocked, as
+/// `add_task_dep` does for new ones, recording the change like it.
+fn m0015_task_blocked_backfill(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TEMP TABLE backfill_blocked AS
+            SELECT t.id FROM tasks t
+            WHERE t.status = 'pending' AND EXISTS (
+                SELECT 1 FROM task_deps d JOIN tasks b ON b.id = d.blocker_id
+                WHERE d.blocked_id = t.id AND b.status NOT IN ('completed', 'deleted'));
+
+        UPDATE tasks SET status = 'blocked', updated_at = datetime('now')
+        WHERE id IN (SELECT id FROM backfill_blocked);
+
+        INSERT INTO task_events (task_id, field, old_value, new_value, actor)
+        SELECT id, 'status', 'pending', 'blocked', 'dependencies' FROM backfill_blocked;
+
+        DROP TABLE backfill_blocked;"
+    )
+}
+
+/// API message id of transcript entries. Every line of one response repeats
+/// its usage, so usage is counted once per message id.
+fn m0016_conversation_message_id(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "conversations", "message_id", "TEXT")?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_conversations_message_id
+            ON conversations(message_id) WHERE message_id IS NOT NULL;"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_are_strictly_increasing() {
+        for pair in MIGRATIONS.windows(2) {
+            assert!(pair[0].version < pair[1].version);
+        }
+        assert_eq!(MIGRATIONS[0].version, 1);
+    }
+
+    #[test]
+    fn test_migrate_to_records_versions() {
+        let conn = Connection::open_in_memory().unwrap();
+        let applied = migrate_to(&conn, latest_version()).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(status(&conn).unwrap().iter().all(|s| s.applied_at.is_some()));
+
+        // Second run is a no-op
+        assert!(migrate_to(&conn, latest_version()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_to_partial() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate_to(&conn, 1).unwrap(), vec![1]);
+        assert_eq!(current_version(&conn).unwrap(), 1);
+        let st = status(&conn).unwrap();
+        assert!(st[0].applied_at.is_some());
+        assert!(st[1].applied_at.is_none());
+
+        let applied = migrate_to(&conn, latest_version()).unwrap();
+        assert_eq!(applied.first(), Some(&2));
+    }
+
+    #[test]
+    fn test_backfill_blocks_pending_tasks_with_open_blockers() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 14).unwrap();
+        conn.execute_batch(
+            "INSERT INTO tasks (id, project, subject, status) VALUES
+                (1, 'p', 'open blocker', 'in_progress'),
+                (2, 'p', 'blocked by 1', 'pending'),
+                (3, 'p', 'done blocker', 'completed'),
+                (4, 'p', 'blocked by 3', 'pending'),
+                (5, 'p', 'started anyway', 'in_progress');
+            INSERT INTO task_deps (blocker_id, blocked_id) VALUES (1, 2), (3, 4), (1, 5);"
+        ).unwrap();
+        migrate_to(&conn, latest_version()).unwrap();
+
+        let statuses: Vec<String> = conn.prepare("SELECT status FROM tasks ORDER BY id").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(statuses, ["in_progress", "blocked", "completed", "pending", "in_progress"]);
+        let events: Vec<(i64, String)> = conn.prepare("SELECT task_id, actor FROM task_events").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(events, [(2, "dependencies".to_string())]);
+    }
+
+    #[test]
+    fn test_migrate_legacy_database() {
+        // Pre-migration databases already have some ALTER TABLE columns
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE conversations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                project TEXT,
+                entry_type TEXT,
+                raw_id INTEGER,
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );"
+        ).unwrap();
+        migrate_to(&conn, latest_version()).unwrap();
+        conn.execute(
+            "INSERT INTO conversations (session_id, role, content, model) VALUES ('s', 'user', 'x', 'm')",
+            [],
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, latest_version()).unwrap();
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES (?1, 'future')",
+            params![latest_version() + 1],
+        ).unwrap();
+        let err = migrate_to(&conn, latest_version()).unwrap_err().to_string();
+        assert!(err.contains("newer than this binary supports"));
+    }
+
+    #[test]
+    fn test_migrate_to_unknown_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        let err = migrate_to(&conn, latest_version() + 1).unwrap_err().to_string();
+        assert!(err.contains("Unknown schema version"));
+    }
+}
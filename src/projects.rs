@@ -0,0 +1,214 @@
+//! Project identity: which project a working directory belongs to.
+//!
+//! A `leafhill.config.md` with a `name:` field (the nearest one between the
+//! directory and its git root) names the project outright. Otherwise the
+//! project is looked up in the registry by git remote, then by git root, and
+//! registered under the shortest unused name on first sight. Outside git the
+//! directory name is used. Aliases map old names to a registered project.
+
+use std::path::{Path, PathBuf};
+
+use crate::db::Database;
+use crate::transcript::project_from_cwd;
+
+pub const CONFIG_FILE: &str = "leafhill.config.md";
+
+/// What a working directory says about its project.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectLocation {
+    /// `name:` from the nearest `leafhill.config.md`.
+    pub config_name: Option<String>,
+    /// Normalized URL of the `origin` remote (else the first remote), e.g.
+    /// `github.com/org/api`.
+    pub git_remote: Option<String>,
+    /// Top of the git working tree.
+    pub git_root: Option<String>,
+    /// Names for a new project, most preferred first: the repository or
+    /// directory name, then longer forms that tell same-named repos apart.
+    pub candidates: Vec<String>,
+}
+
+impl ProjectLocation {
+    pub fn detect(cwd: &str) -> Self {
+        let cwd_path = Path::new(cwd.trim_end_matches('/'));
+        let git = if cwd.is_empty() { None } else { find_git(cwd_path) };
+        let search_top = git.as_ref().map_or(cwd_path, |(root, _)| root.as_path());
+        let config_name = cwd_path.ancestors()
+            .take_while(|dir| dir.starts_with(search_top))
+            .find_map(|dir| config_name(&dir.join(CONFIG_FILE)));
+        let git_remote = git.as_ref().and_then(|(_, git_dir)| remote_url(git_dir)).map(|u| normalize_remote(&u));
+
+        let candidates = match (&git_remote, &git) {
+            (Some(remote), _) => suffixes(remote),
+            (None, Some((root, _))) => suffixes(&root.display().to_string()),
+            (None, None) => vec![project_from_cwd(cwd).to_string()],
+        };
+        ProjectLocation {
+            config_name,
+            git_remote,
+            git_root: git.map(|(root, _)| root.display().to_string()),
+            candidates,
+        }
+    }
+}
+
+/// `a/b/c` as `c`, `b/c`, `a/b/c`.
+fn suffixes(path: &str) -> Vec<String> {
+    let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    (1..=parts.len()).map(|n| parts[parts.len() - n..].join("/")).collect()
+}
+
+/// The working tree root containing `start` and its git directory. A `.git`
+/// file (worktrees, submodules) points at the real one; a worktree's config
+/// lives in the common directory it names.
+fn find_git(start: &Path) -> Option<(PathBuf, PathBuf)> {
+    for dir in start.ancestors() {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return Some((dir.to_path_buf(), dot_git));
+        }
+        if dot_git.is_file() {
+            let text = std::fs::read_to_string(&dot_git).ok()?;
+            let git_dir = dir.join(text.trim().strip_prefix("gitdir:")?.trim());
+            let common = std::fs::read_to_string(git_dir.join("commondir")).ok()
+                .map(|c| git_dir.join(c.trim()))
+                .unwrap_or(git_dir);
+            return Some((dir.to_path_buf(), common));
+        }
+    }
+    None
+}
+
+/// The `origin` remote's URL from `git_dir/config`, else the first remote's.
+fn remote_url(git_dir: &Path) -> Option<String> {
+    let config = std::fs::read_to_string(git_dir.join("config")).ok()?;
+    let mut remote: Option<&str> = None;
+    let mut urls: Vec<(&str, String)> = Vec::new();
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            remote = line.strip_prefix("[remote \"").and_then(|r| r.strip_suffix("\"]"));
+        } else if let (Some(name), Some(value)) = (remote, line.strip_prefix("url")) {
+            if let Some(url) = value.trim_start().strip_prefix('=') {
+                urls.push((name, url.trim().to_string()));
+            }
+        }
+    }
+    urls.iter().find(|(name, _)| *name == "origin").or(urls.first()).map(|(_, url)| url.clone())
+}
+
+/// `host/path` for a remote URL, so the SSH and HTTPS forms of a repository
+/// match: `git@github.com:Org/api.git` and `https://github.com/Org/api`
+/// both become `github.com/Org/api`.
+pub fn normalize_remote(url: &str) -> String {
+    let url = url.trim();
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').unwrap_or((rest, "")),
+        None => url.split_once(':').unwrap_or(("", url)),
+    };
+    let host = host.rsplit('@').next().unwrap_or(host);
+    let host = host.split(':').next().unwrap_or(host).to_lowercase();
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() { path.to_string() } else { format!("{}/{}", host, path) }
+}
+
+/// The `name:` value of a `leafhill.config.md`, if set.
+fn config_name(path: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(path).ok()?;
+    text.lines()
+        .find_map(|line| line.trim().strip_prefix("name:"))
+        .map(|v| v.trim().trim_matches(|c| c == '`' || c == '"' || c == '\'').trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// The project for `cwd`, registering it if new. If the registry cannot be
+/// used, falls back to the name the directory suggests.
+pub fn resolve(db: &Database, cwd: &str) -> String {
+    let location = ProjectLocation::detect(cwd);
+    db.resolve_project(&location).unwrap_or_else(|e| {
+        eprintln!("leafhill: project registry unavailable: {}", e);
+        location.config_name.or_else(|| location.candidates.into_iter().next())
+            .unwrap_or_else(|| project_from_cwd(cwd).to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("leafhill-projects-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn git_repo(dir: &Path, remote: Option<&str>) {
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        let mut config = "[core]\n\tbare = false\n".to_string();
+        if let Some(url) = remote {
+            config.push_str(&format!("[remote \"upstream\"]\n\turl = https://example.com/fork.git\n[remote \"origin\"]\n\turl = {}\n", url));
+        }
+        std::fs::write(dir.join(".git/config"), config).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_remote() {
+        assert_eq!(normalize_remote("git@GitHub.com:Org/api.git"), "github.com/Org/api");
+        assert_eq!(normalize_remote("https://user@github.com/Org/api/"), "github.com/Org/api");
+        assert_eq!(normalize_remote("ssh://git@git.example.com:2222/team/api.git"), "git.example.com/team/api");
+        assert_eq!(normalize_remote("/srv/git/api.git"), "srv/git/api");
+    }
+
+    #[test]
+    fn test_detect_location() {
+        let dir = temp_dir("detect");
+        let repo = dir.join("api");
+        git_repo(&repo, Some("git@github.com:org/api.git"));
+        std::fs::create_dir_all(repo.join("services/billing/src")).unwrap();
+        std::fs::write(repo.join("services/billing").join(CONFIG_FILE), "## Project Info\n\nname: `billing`\ndescription:\n").unwrap();
+
+        let root = ProjectLocation::detect(&format!("{}/", repo.display()));
+        assert_eq!(root.git_remote.as_deref(), Some("github.com/org/api"));
+        assert_eq!(root.git_root, Some(repo.display().to_string()));
+        assert_eq!(root.config_name, None);
+        assert_eq!(root.candidates, ["api", "org/api", "github.com/org/api"]);
+
+        let billing = ProjectLocation::detect(&repo.join("services/billing/src").display().to_string());
+        assert_eq!(billing.config_name.as_deref(), Some("billing"));
+
+        // Configs above the git root belong to something else
+        std::fs::write(dir.join(CONFIG_FILE), "name: outer\n").unwrap();
+        assert_eq!(ProjectLocation::detect(&repo.display().to_string()).config_name, None);
+
+        let plain = ProjectLocation::detect("/nonexistent/leafhill/plain/");
+        assert_eq!((plain.git_root, plain.candidates), (None, vec!["plain".to_string()]));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resolve_registers_and_disambiguates() {
+        let dir = temp_dir("resolve");
+        let (a, b, local) = (dir.join("a/api"), dir.join("b/api"), dir.join("c/api"));
+        git_repo(&a, Some("git@github.com:alpha/api.git"));
+        git_repo(&b, Some("https://github.com/beta/api"));
+        git_repo(&local, None);
+        let db = Database::open_in_memory().unwrap();
+
+        assert_eq!(resolve(&db, &a.display().to_string()), "api");
+        assert_eq!(resolve(&db, &b.display().to_string()), "beta/api");
+        assert_eq!(resolve(&db, &local.join("src").display().to_string()), "c/api");
+
+        // A moved checkout keeps its project through the remote
+        let moved = dir.join("moved/api2");
+        git_repo(&moved, Some("https://github.com/alpha/api.git"));
+        assert_eq!(resolve(&db, &moved.display().to_string()), "api");
+        let projects = db.list_projects().unwrap();
+        assert_eq!(projects.len(), 3);
+        assert_eq!(projects[0].git_remotes, ["github.com/alpha/api"]);
+        assert!(projects[0].git_roots.contains(&moved.display().to_string()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
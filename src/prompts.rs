@@ -0,0 +1,285 @@
+use serde_json::{json, Value};
+
+use crate::db::{ConversationEntry, Database, Memory, Task};
+use crate::embedding::{self, SearchMode};
+use crate::mcp::RpcError;
+
+/// Built-in prompt templates. Arguments are strings, per the MCP spec.
+pub fn list() -> Value {
+    json!({
+        "prompts": [
+            {
+                "name": "recap_project",
+                "description": "Recap a project: open tasks, recent session summaries and relevant memories",
+                "arguments": [
+                    { "name": "project", "description": "Project name", "required": true },
+                    { "name": "limit", "description": "Max summaries and memories to include (default 10)", "required": false }
+                ]
+            },
+            {
+                "name": "resume_session",
+                "description": "Pick up a previous session from its recorded summaries",
+                "arguments": [
+                    { "name": "session_id", "description": "Session ID to resume", "required": true },
+                    { "name": "limit", "description": "Max summaries to include (default 50)", "required": false }
+                ]
+            },
+            {
+                "name": "daily_standup",
+                "description": "Draft a standup update (done / doing / blocked) from recent task activity and summaries",
+                "arguments": [
+                    { "name": "project", "description": "Limit to one project (default: all projects)", "required": false },
+                    { "name": "hours", "description": "Look-back window in hours (default 24)", "required": false }
+                ]
+            }
+        ]
+    })
+}
+
+/// Render a prompt by name with the given arguments.
+pub fn get(db: &Database, name: &str, args: &Value) -> Result<Value, RpcError> {
+    let (description, text) = match name {
+        "recap_project" => {
+            let project = required(args, "project")?;
+            let limit = number(args, "limit", 10)?;
+            (format!("Recap of project {}", project), recap_project(db, project, limit)?)
+        }
+        "resume_session" => {
+            let session_id = required(args, "session_id")?;
+            let limit = number(args, "limit", 50)?;
+            (format!("Resume session {}", session_id), resume_session(db, session_id, limit)?)
+        }
+        "daily_standup" => {
+            let project = args.get("project").and_then(|v| v.as_str());
+            let hours = number(args, "hours", 24)?;
+            let scope = project.map(|p| format!("project {}", p)).unwrap_or_else(|| "all projects".to_string());
+            (format!("Daily standup for {}", scope), daily_standup(db, project, hours)?)
+        }
+        _ => {
+            return Err(RpcError::invalid_params(format!(
+                "Unknown prompt '{}'. Must be one of: recap_project, resume_session, daily_standup", name
+            )))
+        }
+    };
+
+    Ok(json!({
+        "description": description,
+        "messages": [{
+            "role": "user",
+            "content": { "type": "text", "text": text }
+        }]
+    }))
+}
+
+fn required<'a>(args: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    args.get(name)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| RpcError::invalid_params(format!("missing '{}'", name)))
+}
+
+/// Read an optional numeric argument, accepting either a string or a number.
+fn number(args: &Value, name: &str, default: usize) -> Result<usize, RpcError> {
+    match args.get(name) {
+        None | Some(Value::Null) => Ok(default),
+        Some(Value::Number(n)) => n.as_u64()
+            .map(|n| n as usize)
+            .ok_or_else(|| RpcError::invalid_params(format!("'{}' must be a positive integer", name))),
+        Some(Value::String(s)) => s.parse::<usize>()
+            .map_err(|_| RpcError::invalid_params(format!("'{}' must be a positive integer", name))),
+        Some(_) => Err(RpcError::invalid_params(format!("'{}' must be a positive integer", name))),
+    }
+}
+
+fn open_tasks(db: &Database, project: Option<&str>) -> Result<Vec<Task>, RpcError> {
+    let mut tasks = Vec::new();
+    for status in ["in_progress", "blocked", "pending"] {
+        tasks.extend(db.list_tasks(project, Some(status), None, None, None, false, 100).map_err(RpcError::db)?);
+    }
+    Ok(tasks)
+}
+
+fn recap_project(db: &Database, project: &str, limit: usize) -> Result<String, RpcError> {
+    let tasks = open_tasks(db, Some(project))?;
+    let summaries = db.recent_summaries(Some(project), None, limit).map_err(RpcError::db)?;
+
+    // Match memories against the project name and what is being worked on
+    let mut query = project.to_string();
+    for t in &tasks {
+        query.push(' ');
+        query.push_str(&t.subject);
+    }
+    let memories = if db.embedder_model().is_some() {
+        db.semantic_search_memories(&query, None, limit, SearchMode::Hybrid, false)
+    } else {
+        db.search_memories(&embedding::fts_any_terms(&query), None, limit, false)
+    }
+    .map_err(RpcError::db)?;
+
+    let mut out = format!(
+        "Recap the current state of project \"{}\": what was done recently, what is still open, \
+         and what to pick up next. Use the context below, and the memory tools if you need more detail.\n",
+        project
+    );
+    push_tasks(&mut out, "Open tasks", &tasks);
+    push_summaries(&mut out, "Recent session summaries", &summaries);
+    push_memories(&mut out, "Relevant memories", &memories);
+    Ok(out)
+}
+
+fn resume_session(db: &Database, session_id: &str, limit: usize) -> Result<String, RpcError> {
+    let entries = db.get_conversation_context(session_id, limit).map_err(RpcError::db)?;
+    let mut out = format!(
+        "We are resuming session {}. Read the summaries recorded so far, then briefly restate \
+         where we left off and what the next step is.\n",
+        session_id
+    );
+    out.push_str("\n## Session summaries (oldest first)\n\n");
+    if entries.is_empty() {
+        out.push_str("(none recorded)\n");
+    }
+    for e in &entries {
+        out.push_str(&format!("- [{}] {}\n", e.created_at, e.content));
+    }
+    Ok(out)
+}
+
+fn daily_standup(db: &Database, project: Option<&str>, hours: usize) -> Result<String, RpcError> {
+    let since = (chrono::Utc::now() - chrono::Duration::hours(hours as i64))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    let touched = db.tasks_updated_since(project, &since).map_err(RpcError::db)?;
+    let done: Vec<Task> = touched.iter().filter(|t| t.status == "completed").cloned().collect();
+    let open = open_tasks(db, project)?;
+    let doing: Vec<Task> = open.iter().filter(|t| t.status == "in_progress").cloned().collect();
+    let blocked: Vec<Task> = open.iter().filter(|t| t.status == "blocked").cloned().collect();
+    let summaries = db.recent_summaries(project, Some(&since), 50).map_err(RpcError::db)?;
+
+    let scope = project.map(|p| format!("project \"{}\"", p)).unwrap_or_else(|| "all projects".to_string());
+    let mut out = format!(
+        "Write a short daily standup update for {} covering the last {} hours, \
+         with three sections: Done, Doing, Blocked. Base it on the activity below.\n",
+        scope, hours
+    );
+    push_tasks(&mut out, "Completed", &done);
+    push_tasks(&mut out, "In progress", &doing);
+    push_tasks(&mut out, "Blocked", &blocked);
+    push_summaries(&mut out, "Session summaries", &summaries);
+    Ok(out)
+}
+
+fn push_tasks(out: &mut String, heading: &str, tasks: &[Task]) {
+    out.push_str(&format!("\n## {}\n\n", heading));
+    if tasks.is_empty() {
+        out.push_str("(none)\n");
+    }
+    for t in tasks {
+        let mut meta = vec![t.status.clone()];
+        if let Some(p) = &t.priority {
+            meta.push(p.clone());
+        }
+        if let Some(a) = &t.assignee {
+            meta.push(format!("assignee: {}", a));
+        }
+        out.push_str(&format!("- #{} [{}] {}\n", t.id, meta.join(", "), t.subject));
+    }
+}
+
+fn push_summaries(out: &mut String, heading: &str, entries: &[ConversationEntry]) {
+    out.push_str(&format!("\n## {}\n\n", heading));
+    if entries.is_empty() {
+        out.push_str("(none)\n");
+    }
+    for e in entries {
+        out.push_str(&format!("- [{}] ({}) {}\n", e.created_at, e.session_id, e.content));
+    }
+}
+
+fn push_memories(out: &mut String, heading: &str, memories: &[Memory]) {
+    out.push_str(&format!("\n## {}\n\n", heading));
+    if memories.is_empty() {
+        out.push_str("(none)\n");
+    }
+    for m in memories {
+        out.push_str(&format!("- {}/{}: {}\n", m.category, m.key, m.value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt_text(result: &Value) -> &str {
+        result["messages"][0]["content"]["text"].as_str().unwrap()
+    }
+
+    fn seed() -> Database {
+        let db = Database::open_in_memory().unwrap();
+        let t = db.create_task("alpha", "Ship parser", None, Some("high"), None, None, None, None, Some("sam"), None, None, None).unwrap();
+        db.update_task(t.id, &json!({"status": "in_progress"})).unwrap();
+        let done = db.create_task("alpha", "Write changelog", None, None, None, None, None, None, None, None, None, None).unwrap();
+        db.update_task(done.id, &json!({"status": "completed"})).unwrap();
+        db.create_task("beta", "Other project task", None, None, None, None, None, None, None, None, None, None).unwrap();
+        db.log_conversation("s1-alpha", "summary", "Refactored the parser", Some("alpha"), Some("summary"), None).unwrap();
+        db.log_conversation("s2-beta", "summary", "Beta work", Some("beta"), Some("summary"), None).unwrap();
+        db.store_memory("decisions", "parser", "The parser uses a hand-written lexer", None, None).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_list_prompts() {
+        let names: Vec<String> = list()["prompts"].as_array().unwrap().iter()
+            .map(|p| p["name"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, vec!["recap_project", "resume_session", "daily_standup"]);
+    }
+
+    #[test]
+    fn test_recap_project() {
+        let db = seed();
+        let result = get(&db, "recap_project", &json!({"project": "alpha"})).unwrap();
+        let text = prompt_text(&result);
+        assert!(text.contains("Ship parser"));
+        assert!(text.contains("assignee: sam"));
+        assert!(!text.contains("Write changelog")); // completed, not open
+        assert!(!text.contains("Other project task"));
+        assert!(text.contains("Refactored the parser"));
+        assert!(!text.contains("Beta work"));
+        assert!(text.contains("hand-written lexer"));
+    }
+
+    #[test]
+    fn test_resume_session() {
+        let db = seed();
+        let result = get(&db, "resume_session", &json!({"session_id": "s1-alpha", "limit": "5"})).unwrap();
+        assert!(prompt_text(&result).contains("Refactored the parser"));
+
+        let empty = get(&db, "resume_session", &json!({"session_id": "nope"})).unwrap();
+        assert!(prompt_text(&empty).contains("(none recorded)"));
+    }
+
+    #[test]
+    fn test_daily_standup() {
+        let db = seed();
+        let result = get(&db, "daily_standup", &json!({"project": "alpha"})).unwrap();
+        let text = prompt_text(&result);
+        let done = text.find("## Completed").unwrap();
+        let doing = text.find("## In progress").unwrap();
+        assert!(text[done..doing].contains("Write changelog"));
+        assert!(text[doing..].contains("Ship parser"));
+        assert!(!text.contains("Other project task"));
+
+        let all = get(&db, "daily_standup", &json!({})).unwrap();
+        assert!(prompt_text(&all).contains("Beta work"));
+    }
+
+    #[test]
+    fn test_get_errors() {
+        let db = seed();
+        assert!(get(&db, "recap_project", &json!({})).unwrap_err().message.contains("missing 'project'"));
+        assert!(get(&db, "daily_standup", &json!({"hours": "soon"})).is_err());
+        let err = get(&db, "nope", &json!({})).unwrap_err();
+        assert_eq!(err.code, crate::mcp::INVALID_PARAMS);
+        assert!(err.message.contains("Unknown prompt"));
+    }
+}
@@ -14,6 +14,7 @@ fn create_tables_ddl() -> Vec<(&'static str, &'static str)> {
                 tags TEXT, \
                 created_at TEXT NOT NULL, \
                 updated_at TEXT NOT NULL, \
+                expires_at TEXT, \
                 UNIQUE(category, \"key\"))"),
         ("conversations",
             "CREATE TABLE IF NOT EXISTS conversations (\
@@ -30,7 +31,9 @@ fn create_tables_ddl() -> Vec<(&'static str, &'static str)> {
                 cache_creation_tokens BIGINT, \
                 cache_read_tokens BIGINT, \
                 message_timestamp TEXT, \
-                created_at TEXT NOT NULL)"),
+                created_at TEXT NOT NULL, \
+                message_uuid TEXT, \
+                message_id TEXT)"),
         ("tasks",
             "CREATE TABLE IF NOT EXISTS tasks (\
                 id BIGINT PRIMARY KEY, \
@@ -47,7 +50,8 @@ fn create_tables_ddl() -> Vec<(&'static str, &'static str)> {
                 owner TEXT, \
                 session_id TEXT, \
                 created_at TEXT NOT NULL, \
-                updated_at TEXT NOT NULL)"),
+                updated_at TEXT NOT NULL, \
+                recurrence TEXT)"),
         ("task_deps",
             "CREATE TABLE IF NOT EXISTS task_deps (\
                 blocker_id BIGINT NOT NULL, \
@@ -66,6 +70,17 @@ fn create_tables_ddl() -> Vec<(&'static str, &'static str)> {
     ]
 }
 
+/// Columns added after a table's first release, so targets created by an
+/// older export are brought up to date before inserting.
+fn add_columns_ddl() -> Vec<(&'static str, &'static str)> {
+    vec![
+        ("memories", "ALTER TABLE memories ADD COLUMN IF NOT EXISTS expires_at TEXT"),
+        ("tasks", "ALTER TABLE tasks ADD COLUMN IF NOT EXISTS recurrence TEXT"),
+        ("conversations", "ALTER TABLE conversations ADD COLUMN IF NOT EXISTS message_uuid TEXT"),
+        ("conversations", "ALTER TABLE conversations ADD COLUMN IF NOT EXISTS message_id TEXT"),
+    ]
+}
+
 async fn export_table(
     db: &Database,
     client: &Client,
@@ -77,8 +92,8 @@ async fn export_table(
     match table {
         "memories" => {
             let stmt = client.prepare(
-                "INSERT INTO memories (id, category, \"key\", value, tags, created_at, updated_at) \
-                 VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT DO NOTHING"
+                "INSERT INTO memories (id, category, \"key\", value, tags, created_at, updated_at, expires_at) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT DO NOTHING"
             ).await.map_err(|e| format!("Failed to prepare memories insert: {}", e))?;
 
             let memories = db.export_all_memories()
@@ -88,7 +103,7 @@ async fn export_table(
                 let tags_json = mem.tags.as_ref().map(|t| serde_json::to_string(t).unwrap_or_default());
                 let rows = client.execute(&stmt, &[
                     &mem.id, &mem.category, &mem.key, &mem.value,
-                    &tags_json, &mem.created_at, &mem.updated_at,
+                    &tags_json, &mem.created_at, &mem.updated_at, &mem.expires_at,
                 ]).await.map_err(|e| format!("Failed to insert memory {}: {}", mem.id, e))?;
                 if rows > 0 { inserted += 1; } else { skipped += 1; }
             }
@@ -98,8 +113,8 @@ async fn export_table(
             let stmt = client.prepare(
                 "INSERT INTO conversations (id, session_id, role, content, project, entry_type, raw_id, \
                  model, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, \
-                 message_timestamp, created_at) \
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14) ON CONFLICT DO NOTHING"
+                 message_timestamp, created_at, message_uuid, message_id) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16) ON CONFLICT DO NOTHING"
             ).await.map_err(|e| format!("Failed to prepare conversations insert: {}", e))?;
 
             let entries = db.export_all_conversations()
@@ -111,7 +126,7 @@ async fn export_table(
                     &entry.project, &entry.entry_type, &entry.raw_id,
                     &entry.model, &entry.input_tokens, &entry.output_tokens,
                     &entry.cache_creation_tokens, &entry.cache_read_tokens,
-                    &entry.message_timestamp, &entry.created_at,
+                    &entry.message_timestamp, &entry.created_at, &entry.message_uuid, &entry.message_id,
                 ]).await.map_err(|e| format!("Failed to insert conversation {}: {}", entry.id, e))?;
                 if rows > 0 { inserted += 1; } else { skipped += 1; }
             }
@@ -120,8 +135,8 @@ async fn export_table(
         "tasks" => {
             let stmt = client.prepare(
                 "INSERT INTO tasks (id, project, subject, description, status, priority, task_type, \
-                 parent_id, due_date, created_by, assignee, owner, session_id, created_at, updated_at) \
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15) ON CONFLICT DO NOTHING"
+                 parent_id, due_date, created_by, assignee, owner, session_id, created_at, updated_at, recurrence) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16) ON CONFLICT DO NOTHING"
             ).await.map_err(|e| format!("Failed to prepare tasks insert: {}", e))?;
 
             let tasks = db.export_all_tasks()
@@ -132,7 +147,7 @@ async fn export_table(
                     &task.id, &task.project, &task.subject, &task.description,
                     &task.status, &task.priority, &task.task_type, &task.parent_id,
                     &task.due_date, &task.created_by, &task.assignee, &task.owner,
-                    &task.session_id, &task.created_at, &task.updated_at,
+                    &task.session_id, &task.created_at, &task.updated_at, &task.recurrence,
                 ]).await.map_err(|e| format!("Failed to insert task {}: {}", task.id, e))?;
                 if rows > 0 { inserted += 1; } else { skipped += 1; }
             }
@@ -184,10 +199,10 @@ pub async fn run_export(
     tables: &[String],
 ) -> Result<(), String> {
     if !url.starts_with("postgres://") && !url.starts_with("postgresql://") {
-        return Err(format!(
+        return Err(
             "Only PostgreSQL is currently supported. URL must start with postgres:// or postgresql://. \
-             MySQL/MariaDB support will be added when the Rust toolchain supports sqlx."
-        ));
+             MySQL/MariaDB support will be added when the Rust toolchain supports sqlx.".to_string()
+        );
     }
 
     let (client, connection) = tokio_postgres::connect(url, NoTls)
@@ -210,6 +225,13 @@ pub async fn run_export(
                 .map_err(|e| format!("Failed to create table '{}': {}", table_name, e))?;
         }
     }
+    for (table_name, ddl) in &add_columns_ddl() {
+        if tables.iter().any(|t| t == table_name) {
+            client.execute(*ddl, &[])
+                .await
+                .map_err(|e| format!("Failed to update table '{}': {}", table_name, e))?;
+        }
+    }
 
     // Export data in FK dependency order
     let export_order = ["memories", "conversations", "tasks", "task_deps", "links"];
@@ -0,0 +1,298 @@
+//! Automatic recall: memories, open tasks and session summaries relevant to a
+//! prompt, returned to Claude Code by the UserPromptSubmit hook.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::config::RecallSettings;
+use crate::db::Database;
+use crate::toolcalls::truncate_chars;
+
+/// Settings for recall: the `[recall]` section of the config file (see
+/// `config`), then these environment variables over it:
+///
+/// - `CLAUDE_MEMORY_RECALL` — `on` to enable (default off)
+/// - `CLAUDE_MEMORY_RECALL_LIMIT` — most hits returned (default 5)
+/// - `CLAUDE_MEMORY_RECALL_TOKENS` — token budget for the injected context (default 600)
+/// - `CLAUDE_MEMORY_RECALL_MIN_SCORE` — minimum relevance, 0 to 1 (default 0.4)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecallConfig {
+    pub enabled: bool,
+    pub limit: usize,
+    pub token_budget: usize,
+    pub min_score: f64,
+}
+
+impl Default for RecallConfig {
+    fn default() -> Self {
+        RecallConfig { enabled: false, limit: 5, token_budget: 600, min_score: 0.4 }
+    }
+}
+
+impl RecallConfig {
+    pub fn load(settings: &RecallSettings) -> Result<Self, String> {
+        Self::from_vars(Self::from_settings(settings), |name| std::env::var(name).ok())
+    }
+
+    fn from_settings(settings: &RecallSettings) -> Self {
+        let defaults = RecallConfig::default();
+        RecallConfig {
+            enabled: settings.enabled.unwrap_or(defaults.enabled),
+            limit: settings.limit.unwrap_or(defaults.limit),
+            token_budget: settings.tokens.unwrap_or(defaults.token_budget),
+            min_score: settings.min_score.unwrap_or(defaults.min_score),
+        }
+    }
+
+    fn from_vars(mut config: RecallConfig, var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        if let Some(v) = var("CLAUDE_MEMORY_RECALL") {
+            config.enabled = match v.trim() {
+                "on" | "1" | "true" => true,
+                "off" | "0" | "false" | "" => false,
+                other => return Err(format!("Invalid CLAUDE_MEMORY_RECALL '{}'. Use on or off", other)),
+            };
+        }
+        if let Some(v) = var("CLAUDE_MEMORY_RECALL_LIMIT") {
+            config.limit = v.trim().parse()
+                .map_err(|_| format!("Invalid CLAUDE_MEMORY_RECALL_LIMIT '{}'. Use a whole number", v))?;
+        }
+        if let Some(v) = var("CLAUDE_MEMORY_RECALL_TOKENS") {
+            config.token_budget = v.trim().parse()
+                .map_err(|_| format!("Invalid CLAUDE_MEMORY_RECALL_TOKENS '{}'. Use a whole number", v))?;
+        }
+        if let Some(v) = var("CLAUDE_MEMORY_RECALL_MIN_SCORE") {
+            config.min_score = v.trim().parse::<f64>().ok()
+                .filter(|s| (0.0..=1.0).contains(s))
+                .ok_or_else(|| format!("Invalid CLAUDE_MEMORY_RECALL_MIN_SCORE '{}'. Use a number from 0 to 1", v))?;
+        }
+        Ok(config)
+    }
+}
+
+/// Where a recalled hit comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecallSource {
+    Memory,
+    Task,
+    Summary,
+}
+
+impl fmt::Display for RecallSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RecallSource::Memory => "memory",
+            RecallSource::Task => "task",
+            RecallSource::Summary => "summary",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecallHit {
+    pub source: RecallSource,
+    pub id: i64,
+    /// Short identification, e.g. `facts/db-path` or `#12 [in_progress]`.
+    pub label: String,
+    pub text: String,
+    /// Relevance from 0 to 1: the share of the prompt's keywords the hit
+    /// contains (see `coverage`).
+    pub score: f64,
+}
+
+impl RecallHit {
+    fn line(&self) -> String {
+        format!("- {} {}: {}", self.source, self.label, self.text)
+    }
+}
+
+/// Longest text kept per hit, in chars.
+const HIT_TEXT_MAX_CHARS: usize = 400;
+
+/// Most prompt terms searched for; long pasted prompts would otherwise
+/// match nearly everything.
+const MAX_QUERY_TERMS: usize = 24;
+
+/// A hit containing this many of the prompt's keywords scores 1, so long
+/// prompts are not penalized for the terms a single hit cannot cover.
+const COVERAGE_TERMS: usize = 6;
+
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "are", "but", "not", "you", "all", "can", "had", "her", "was", "one",
+    "our", "out", "has", "have", "this", "that", "with", "from", "they", "will", "would",
+    "there", "their", "what", "about", "which", "when", "make", "like", "into", "could",
+    "them", "then", "than", "some", "please", "should", "does", "just", "also", "how", "why",
+    "its", "let", "use", "using", "need", "want", "did", "get", "got", "any", "who", "were",
+    "been", "being", "your", "these", "those", "here", "where", "more", "very", "only", "each",
+];
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric()).filter(|t| !t.is_empty()).map(|t| t.to_lowercase())
+}
+
+/// The significant terms of `prompt`: distinct, lowercased, at least three
+/// chars and not a stop word.
+pub fn keywords(prompt: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for term in words(prompt) {
+        if term.chars().count() < 3 || STOP_WORDS.contains(&term.as_str()) || terms.contains(&term) {
+            continue;
+        }
+        terms.push(term);
+        if terms.len() == MAX_QUERY_TERMS {
+            break;
+        }
+    }
+    terms
+}
+
+/// FTS5 query matching any of `keywords`.
+pub fn fts_query(keywords: &[String]) -> String {
+    keywords.iter().map(|t| format!("\"{}\"", t)).collect::<Vec<_>>().join(" OR ")
+}
+
+/// Share of `keywords` found in `text`, counting at most `COVERAGE_TERMS`.
+/// BM25 is not used for this because it is relative to each table: in a
+/// small table a term found in most rows scores near zero however well it
+/// matches.
+pub fn coverage(keywords: &[String], text: &str) -> f64 {
+    if keywords.is_empty() {
+        return 0.0;
+    }
+    let present: HashSet<String> = words(text).collect();
+    let matched = keywords.iter().filter(|k| present.contains(*k)).count();
+    (matched as f64 / keywords.len().min(COVERAGE_TERMS) as f64).min(1.0)
+}
+
+/// Rough token count: about four chars per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+const CONTEXT_HEADER: &str = "[leafhill-memory] Possibly relevant from memory:";
+
+/// The best `candidates` that clear `min_score`, at most `limit` of them,
+/// skipping any that would push the rendered context over the token budget.
+/// Equal scores keep their candidate order.
+pub fn select_hits(mut candidates: Vec<RecallHit>, config: &RecallConfig) -> Vec<RecallHit> {
+    candidates.retain(|h| h.score >= config.min_score);
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut used = estimate_tokens(CONTEXT_HEADER);
+    let mut hits = Vec::new();
+    for hit in candidates {
+        if hits.len() == config.limit {
+            break;
+        }
+        let cost = estimate_tokens(&hit.line()) + 1;
+        if used + cost > config.token_budget {
+            continue;
+        }
+        used += cost;
+        hits.push(hit);
+    }
+    hits
+}
+
+/// The hits to surface for `prompt` in `project`.
+pub fn recall(db: &Database, prompt: &str, project: Option<&str>, config: &RecallConfig) -> rusqlite::Result<Vec<RecallHit>> {
+    let keywords = keywords(prompt);
+    if keywords.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut candidates = db.recall_candidates(&fts_query(&keywords), project, config.limit.max(1) * 2)?;
+    for hit in &mut candidates {
+        hit.score = coverage(&keywords, &format!("{} {}", hit.label, hit.text));
+        hit.text = truncate_chars(&hit.text.split_whitespace().collect::<Vec<_>>().join(" "), HIT_TEXT_MAX_CHARS);
+    }
+    Ok(select_hits(candidates, config))
+}
+
+/// The `additionalContext` text for `hits`.
+pub fn format_context(hits: &[RecallHit]) -> String {
+    let mut lines = vec![CONTEXT_HEADER.to_string()];
+    lines.extend(hits.iter().map(RecallHit::line));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(id: i64, score: f64, text: &str) -> RecallHit {
+        RecallHit { source: RecallSource::Memory, id, label: format!("facts/k{}", id), text: text.to_string(), score }
+    }
+
+    #[test]
+    fn test_config_from_vars() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| pairs.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+        };
+        let base = || RecallConfig::default();
+        assert_eq!(RecallConfig::from_vars(base(), vars(&[])).unwrap(), RecallConfig::default());
+        let config = RecallConfig::from_vars(base(), vars(&[
+            ("CLAUDE_MEMORY_RECALL", "on"),
+            ("CLAUDE_MEMORY_RECALL_LIMIT", "3"),
+            ("CLAUDE_MEMORY_RECALL_MIN_SCORE", "0.5"),
+        ])).unwrap();
+        assert!(config.enabled);
+        assert_eq!((config.limit, config.token_budget, config.min_score), (3, 600, 0.5));
+        assert!(RecallConfig::from_vars(base(), vars(&[("CLAUDE_MEMORY_RECALL", "maybe")])).is_err());
+        assert!(RecallConfig::from_vars(base(), vars(&[("CLAUDE_MEMORY_RECALL_MIN_SCORE", "2")])).is_err());
+        let settings = RecallSettings { enabled: Some(true), tokens: Some(100), ..Default::default() };
+        let from_file = RecallConfig::from_vars(RecallConfig::from_settings(&settings), vars(&[("CLAUDE_MEMORY_RECALL", "off")])).unwrap();
+        assert_eq!((from_file.enabled, from_file.token_budget), (false, 100));
+    }
+
+    #[test]
+    fn test_keywords_and_coverage() {
+        let k = keywords("How do we deploy the API to prod? Deploy!");
+        assert_eq!(fts_query(&k), r#""deploy" OR "api" OR "prod""#);
+        assert!(keywords("how do we?").is_empty());
+        assert_eq!(coverage(&k, "Deploy steps for the api"), 2.0 / 3.0);
+        assert_eq!(coverage(&k, "deployment"), 0.0);
+        let long = keywords("one1 two2 three3 four4 five5 six6 seven7 eight8");
+        assert_eq!(coverage(&long, "one1 two2 three3 four4 five5 six6"), 1.0);
+    }
+
+    #[test]
+    fn test_recall_from_database() {
+        let db = Database::open_in_memory().unwrap();
+        db.store_memory("facts", "deploy", "Deploys go through the staging cluster first", None, None).unwrap();
+        db.store_memory("facts", "colour", "Favourite colour is green", None, None).unwrap();
+        db.create_task("repo", "Fix staging deploy script", None, None, None, None, None, None, None, None, None, None).unwrap();
+        db.create_task("other", "Staging deploy for other", None, None, None, None, None, None, None, None, None, None).unwrap();
+        db.log_conversation("s", "summary", "Debugged the deploy pipeline", Some("repo"), Some("summary"), None).unwrap();
+
+        let config = RecallConfig { enabled: true, limit: 10, token_budget: 1000, min_score: 0.0 };
+        let hits = recall(&db, "why did the staging deploy fail?", Some("repo"), &config).unwrap();
+        let sources: Vec<String> = hits.iter().map(|h| format!("{} {}", h.source, h.label)).collect();
+        assert_eq!(hits.len(), 3, "{:?}", sources);
+        assert!(sources.contains(&"memory facts/deploy".to_string()));
+        assert!(sources.iter().any(|s| s.starts_with("task #1 ")));
+        assert!(hits.iter().all(|h| h.score > 0.0 && h.score < 1.0));
+
+        // The summary mentions only "deploy"; the memory's key counts
+        let strict = RecallConfig { min_score: 0.99, ..config };
+        let full = recall(&db, "staging deploy", Some("repo"), &strict).unwrap();
+        assert_eq!(full.iter().map(|h| h.source).collect::<Vec<_>>(), vec![RecallSource::Memory, RecallSource::Task]);
+        assert!(recall(&db, "how?", Some("repo"), &config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_select_hits() {
+        let config = RecallConfig { enabled: true, limit: 2, token_budget: 40, min_score: 0.3 };
+        let long = "x".repeat(200);
+        let hits = select_hits(vec![
+            hit(1, 0.2, "too weak"),
+            hit(2, 0.9, &long),
+            hit(3, 0.5, "fits"),
+            hit(4, 0.6, "also fits"),
+            hit(5, 0.4, "over the limit"),
+        ], &config);
+        // 2 is over the token budget, 1 under the minimum score
+        assert_eq!(hits.iter().map(|h| h.id).collect::<Vec<_>>(), vec![4, 3]);
+        assert!(format_context(&hits).ends_with("- memory facts/k3: fits"));
+    }
+}
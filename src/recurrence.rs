@@ -0,0 +1,276 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Freq {
+    pub const ALL: &[&str] = &["DAILY", "WEEKLY", "MONTHLY", "YEARLY"];
+}
+
+impl FromStr for Freq {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "DAILY" => Ok(Freq::Daily),
+            "WEEKLY" => Ok(Freq::Weekly),
+            "MONTHLY" => Ok(Freq::Monthly),
+            "YEARLY" => Ok(Freq::Yearly),
+            _ => Err(format!("Invalid FREQ '{}'. Must be one of: {}", s, Self::ALL.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for Freq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Freq::Daily => "DAILY",
+            Freq::Weekly => "WEEKLY",
+            Freq::Monthly => "MONTHLY",
+            Freq::Yearly => "YEARLY",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A task recurrence rule: `daily`, `weekly`, `monthly`, `yearly`, or an
+/// RRULE subset (`FREQ`, `INTERVAL`, `BYDAY` for weekly rules, `BYMONTHDAY`
+/// for monthly rules). Stored in its canonical RRULE form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recurrence {
+    pub freq: Freq,
+    pub interval: u32,
+    pub by_day: Vec<Weekday>,
+    pub by_month_day: Option<u32>,
+}
+
+/// Largest `INTERVAL` accepted: a thousand days, weeks, months or years.
+pub const MAX_INTERVAL: u32 = 1000;
+
+const DAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let keyword = match s.to_ascii_lowercase().as_str() {
+            "daily" => Some(Freq::Daily),
+            "weekly" => Some(Freq::Weekly),
+            "monthly" => Some(Freq::Monthly),
+            "yearly" => Some(Freq::Yearly),
+            _ => None,
+        };
+        if let Some(freq) = keyword {
+            return Ok(Recurrence { freq, interval: 1, by_day: Vec::new(), by_month_day: None });
+        }
+
+        let rule = s.strip_prefix("RRULE:").unwrap_or(s);
+        let mut freq = None;
+        let mut interval = 1;
+        let mut by_day = Vec::new();
+        let mut by_month_day = None;
+        for part in rule.split(';').filter(|p| !p.is_empty()) {
+            let (name, value) = part.split_once('=').ok_or_else(|| format!(
+                "Invalid recurrence '{}'. Must be daily, weekly, monthly, yearly or an RRULE like FREQ=WEEKLY;BYDAY=MO", s
+            ))?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => freq = Some(value.parse::<Freq>()?),
+                "INTERVAL" => {
+                    interval = value.parse::<u32>().ok().filter(|n| (1..=MAX_INTERVAL).contains(n))
+                        .ok_or_else(|| format!("Invalid INTERVAL '{}'. Must be between 1 and {}", value, MAX_INTERVAL))?;
+                }
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        let wd = DAYS.iter().find(|(code, _)| code.eq_ignore_ascii_case(day))
+                            .map(|(_, wd)| *wd)
+                            .ok_or_else(|| format!("Invalid BYDAY '{}'. Must be one of: MO, TU, WE, TH, FR, SA, SU", day))?;
+                        if !by_day.contains(&wd) {
+                            by_day.push(wd);
+                        }
+                    }
+                    by_day.sort_by_key(|d| d.num_days_from_monday());
+                }
+                "BYMONTHDAY" => {
+                    by_month_day = Some(value.parse::<u32>().ok().filter(|d| (1..=31).contains(d))
+                        .ok_or_else(|| format!("Invalid BYMONTHDAY '{}'. Must be between 1 and 31", value))?);
+                }
+                other => {
+                    return Err(format!(
+                        "Invalid RRULE part '{}'. Must be one of: FREQ, INTERVAL, BYDAY, BYMONTHDAY", other
+                    ))
+                }
+            }
+        }
+
+        let freq = freq.ok_or_else(|| format!("Invalid recurrence '{}'. FREQ is required", s))?;
+        if !by_day.is_empty() && freq != Freq::Weekly {
+            return Err("BYDAY is only supported with FREQ=WEEKLY".to_string());
+        }
+        if by_month_day.is_some() && freq != Freq::Monthly {
+            return Err("BYMONTHDAY is only supported with FREQ=MONTHLY".to_string());
+        }
+        Ok(Recurrence { freq, interval, by_day, by_month_day })
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.freq)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<&str> = self.by_day.iter()
+                .map(|wd| DAYS.iter().find(|(_, d)| d == wd).map(|(code, _)| *code).unwrap_or("MO"))
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(d) = self.by_month_day {
+            write!(f, ";BYMONTHDAY={}", d)?;
+        }
+        Ok(())
+    }
+}
+
+impl Recurrence {
+    /// The first occurrence strictly after `date`, or `None` past the last
+    /// date chrono can represent.
+    pub fn next_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let interval = u64::from(self.interval);
+        match self.freq {
+            Freq::Daily => date.checked_add_days(Days::new(interval)),
+            Freq::Weekly if self.by_day.is_empty() => date.checked_add_days(Days::new(7 * interval)),
+            Freq::Weekly => {
+                let week_start = date.checked_sub_days(Days::new(u64::from(date.weekday().num_days_from_monday())))?;
+                let later_this_week = self.by_day.iter()
+                    .filter_map(|wd| week_start.checked_add_days(Days::new(u64::from(wd.num_days_from_monday()))))
+                    .find(|d| *d > date);
+                match later_this_week {
+                    Some(d) => Some(d),
+                    None => week_start.checked_add_days(Days::new(
+                        7 * interval + u64::from(self.by_day[0].num_days_from_monday()),
+                    )),
+                }
+            }
+            Freq::Monthly => {
+                if let Some(day) = self.by_month_day {
+                    let this_month = clamp_day(date.year(), date.month(), day);
+                    if this_month > date {
+                        return Some(this_month);
+                    }
+                }
+                add_months(date, self.interval, self.by_month_day.unwrap_or(date.day()))
+            }
+            Freq::Yearly => add_months(date, 12 * self.interval, date.day()),
+        }
+    }
+
+    /// Due date for the occurrence after one due on `due_date`, skipping any
+    /// occurrences already in the past relative to `today`. A time suffix on
+    /// `due_date` (e.g. ` 09:00`) is carried over; without a parseable due
+    /// date the schedule starts from `today`.
+    pub fn next_due_date(&self, due_date: Option<&str>, today: NaiveDate) -> Result<String, String> {
+        let parsed = due_date.and_then(|d| {
+            let date = NaiveDate::parse_from_str(d.get(..10)?, "%Y-%m-%d").ok()?;
+            Some((date, &d[10..]))
+        });
+        let (base, suffix) = parsed.unwrap_or((today, ""));
+        // Due dates are stored as YYYY-MM-DD, so the schedule ends with year 9999
+        let out_of_range = || format!("Recurrence '{}' has no occurrence after {} before the year 10000", self, base);
+        let mut next = self.next_after(base).ok_or_else(out_of_range)?;
+        while next < today {
+            next = self.next_after(next).ok_or_else(out_of_range)?;
+        }
+        if next.year() > 9999 {
+            return Err(out_of_range());
+        }
+        Ok(format!("{}{}", next.format("%Y-%m-%d"), suffix))
+    }
+}
+
+/// `day` of the month `months` after `date`'s, clamped to that month's end.
+fn add_months(date: NaiveDate, months: u32, day: u32) -> Option<NaiveDate> {
+    let first = date.with_day(1)?.checked_add_months(Months::new(months))?;
+    Some(clamp_day(first.year(), first.month(), day))
+}
+
+/// `day` of the given month, clamped to the month's last day.
+fn clamp_day(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day).rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .expect("every month has a first day")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn rule(s: &str) -> Recurrence {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_canonical_form() {
+        assert_eq!(rule("weekly").to_string(), "FREQ=WEEKLY");
+        assert_eq!(rule("Daily").to_string(), "FREQ=DAILY");
+        assert_eq!(rule("RRULE:FREQ=weekly;INTERVAL=2;BYDAY=th,mo").to_string(), "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH");
+        assert_eq!(rule("FREQ=MONTHLY;BYMONTHDAY=15").to_string(), "FREQ=MONTHLY;BYMONTHDAY=15");
+
+        assert!("fortnightly".parse::<Recurrence>().is_err());
+        assert!("FREQ=HOURLY".parse::<Recurrence>().unwrap_err().contains("Invalid FREQ 'HOURLY'"));
+        assert!("FREQ=DAILY;INTERVAL=0".parse::<Recurrence>().is_err());
+        assert!("FREQ=DAILY;INTERVAL=4000000000".parse::<Recurrence>().unwrap_err().contains("between 1 and 1000"));
+        assert!("FREQ=DAILY;BYDAY=MO".parse::<Recurrence>().is_err());
+        assert!("FREQ=WEEKLY;COUNT=3".parse::<Recurrence>().is_err());
+        assert!("INTERVAL=2".parse::<Recurrence>().unwrap_err().contains("FREQ is required"));
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2026-01-05 is a Monday
+        assert_eq!(rule("daily").next_after(date("2026-01-05")), Some(date("2026-01-06")));
+        assert_eq!(rule("weekly").next_after(date("2026-01-05")), Some(date("2026-01-12")));
+        assert_eq!(rule("FREQ=WEEKLY;BYDAY=MO,TH").next_after(date("2026-01-05")), Some(date("2026-01-08")));
+        assert_eq!(rule("FREQ=WEEKLY;BYDAY=MO,TH").next_after(date("2026-01-08")), Some(date("2026-01-12")));
+        assert_eq!(rule("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO").next_after(date("2026-01-05")), Some(date("2026-01-19")));
+        assert_eq!(rule("monthly").next_after(date("2026-01-31")), Some(date("2026-02-28")));
+        assert_eq!(rule("FREQ=MONTHLY;BYMONTHDAY=15").next_after(date("2026-01-03")), Some(date("2026-01-15")));
+        assert_eq!(rule("FREQ=MONTHLY;BYMONTHDAY=31").next_after(date("2026-01-31")), Some(date("2026-02-28")));
+        assert_eq!(rule("FREQ=MONTHLY;INTERVAL=3").next_after(date("2026-11-10")), Some(date("2027-02-10")));
+        assert_eq!(rule("yearly").next_after(date("2028-02-29")), Some(date("2029-02-28")));
+        assert_eq!(rule("FREQ=DAILY;INTERVAL=1000").next_after(NaiveDate::MAX), None);
+        assert_eq!(rule("FREQ=YEARLY;INTERVAL=1000").next_after(NaiveDate::MAX), None);
+    }
+
+    #[test]
+    fn test_next_due_date() {
+        let today = date("2026-01-07");
+        assert_eq!(rule("weekly").next_due_date(Some("2026-01-05"), today).unwrap(), "2026-01-12");
+        // Overdue occurrences are skipped rather than piling up
+        assert_eq!(rule("weekly").next_due_date(Some("2025-12-01"), today).unwrap(), "2026-01-12");
+        assert_eq!(rule("daily").next_due_date(Some("2026-01-06 09:00"), today).unwrap(), "2026-01-07 09:00");
+        assert_eq!(rule("daily").next_due_date(None, today).unwrap(), "2026-01-08");
+        assert_eq!(rule("daily").next_due_date(Some("next sprint"), today).unwrap(), "2026-01-08");
+        assert!(rule("FREQ=YEARLY;INTERVAL=1000").next_due_date(Some("9999-06-01"), today).is_err());
+    }
+}
@@ -0,0 +1,445 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::db::{ConversationEntry, Database};
+use crate::mcp::{self, RpcError};
+
+/// JSON-RPC error code for an unknown resource URI (MCP spec).
+pub const RESOURCE_NOT_FOUND: i64 = -32002;
+
+/// Entries per `resources/list` page.
+const PAGE_SIZE: usize = 100;
+
+/// How often subscribed resources are re-checked for changes made by other
+/// processes (hooks, CLI, other servers).
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A resource addressed by URI:
+/// `memory://{category}/{key}`, `task://{id}` or `session://{session_id}`.
+/// Path segments are percent-encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceUri {
+    Memory { category: String, key: String },
+    Task(i64),
+    Session(String),
+}
+
+impl FromStr for ResourceUri {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = s.split_once("://")
+            .ok_or_else(|| format!("Invalid resource URI '{}'", s))?;
+        match scheme {
+            "memory" => {
+                let (category, key) = rest.split_once('/')
+                    .ok_or_else(|| format!("Invalid memory URI '{}'. Expected memory://{{category}}/{{key}}", s))?;
+                let category = percent_decode(category)?;
+                let key = percent_decode(key)?;
+                if category.is_empty() || key.is_empty() {
+                    return Err(format!("Invalid memory URI '{}'. Expected memory://{{category}}/{{key}}", s));
+                }
+                Ok(ResourceUri::Memory { category, key })
+            }
+            "task" => rest.parse::<i64>()
+                .map(ResourceUri::Task)
+                .map_err(|_| format!("Invalid task URI '{}'. Expected task://{{id}}", s)),
+            "session" => {
+                let sid = percent_decode(rest)?;
+                if sid.is_empty() {
+                    return Err(format!("Invalid session URI '{}'. Expected session://{{session_id}}", s));
+                }
+                Ok(ResourceUri::Session(sid))
+            }
+            _ => Err(format!(
+                "Unknown resource scheme '{}'. Must be one of: memory, task, session", scheme
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ResourceUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceUri::Memory { category, key } => {
+                write!(f, "memory://{}/{}", percent_encode(category), percent_encode(key))
+            }
+            ResourceUri::Task(id) => write!(f, "task://{}", id),
+            ResourceUri::Session(sid) => write!(f, "session://{}", percent_encode(sid)),
+        }
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+fn percent_decode(s: &str) -> Result<String, String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!("Invalid percent-encoding in '{}'", s))?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| format!("Invalid UTF-8 in '{}'", s))
+}
+
+fn not_found(uri: &str) -> RpcError {
+    RpcError {
+        code: RESOURCE_NOT_FOUND,
+        message: format!("Resource not found: {}", uri),
+        data: Some(json!({ "uri": uri })),
+    }
+}
+
+pub fn templates() -> Value {
+    json!({
+        "resourceTemplates": [
+            {
+                "uriTemplate": "memory://{category}/{key}",
+                "name": "memory",
+                "description": "A stored memory with its value, tags and timestamps",
+                "mimeType": "application/json"
+            },
+            {
+                "uriTemplate": "task://{id}",
+                "name": "task",
+                "description": "A task with its dependencies and links",
+                "mimeType": "application/json"
+            },
+            {
+                "uriTemplate": "session://{session_id}",
+                "name": "session",
+                "description": "Full transcript of a logged session, oldest entry first",
+                "mimeType": "text/markdown"
+            }
+        ]
+    })
+}
+
+/// List memories, tasks and sessions, paged by an opaque offset cursor.
+pub fn list(db: &Database, cursor: Option<&str>) -> Result<Value, RpcError> {
+    let offset = match cursor {
+        Some(c) => c.parse::<usize>()
+            .map_err(|_| RpcError::invalid_params(format!("Invalid cursor '{}'", c)))?,
+        None => 0,
+    };
+
+    let mut resources: Vec<Value> = Vec::new();
+    for mem in db.export_all_memories().map_err(RpcError::db)? {
+        if mem.is_expired() {
+            continue;
+        }
+        resources.push(json!({
+            "uri": ResourceUri::Memory { category: mem.category.clone(), key: mem.key.clone() }.to_string(),
+            "name": format!("{}/{}", mem.category, mem.key),
+            "description": truncate(&mem.value, 120),
+            "mimeType": "application/json"
+        }));
+    }
+    for task in db.export_all_tasks().map_err(RpcError::db)? {
+        resources.push(json!({
+            "uri": ResourceUri::Task(task.id).to_string(),
+            "name": format!("Task #{}: {}", task.id, task.subject),
+            "description": format!("[{}] project {}", task.status, task.project),
+            "mimeType": "application/json"
+        }));
+    }
+    for session in db.list_sessions(None).map_err(RpcError::db)? {
+        resources.push(json!({
+            "uri": ResourceUri::Session(session.session_id.clone()).to_string(),
+            "name": session.session_id,
+            "description": format!(
+                "{} entries, project {}, last activity {}",
+                session.entries,
+                session.project.as_deref().unwrap_or("-"),
+                session.last_at
+            ),
+            "mimeType": "text/markdown"
+        }));
+    }
+
+    let total = resources.len();
+    let page: Vec<Value> = resources.into_iter().skip(offset).take(PAGE_SIZE).collect();
+    let mut result = json!({ "resources": page });
+    if offset + PAGE_SIZE < total {
+        result["nextCursor"] = json!((offset + PAGE_SIZE).to_string());
+    }
+    Ok(result)
+}
+
+/// Read a resource's contents.
+pub fn read(db: &Database, uri: &str) -> Result<Value, RpcError> {
+    let parsed: ResourceUri = uri.parse().map_err(RpcError::invalid_params)?;
+    let (mime, text) = match &parsed {
+        ResourceUri::Memory { category, key } => {
+            let mem = db.get_memory(category, key)
+                .map_err(RpcError::db)?
+                .ok_or_else(|| not_found(uri))?;
+            ("application/json", serde_json::to_string_pretty(&mem).unwrap_or_default())
+        }
+        ResourceUri::Task(id) => {
+            let detail = match mcp::task_detail(db, *id) {
+                Ok(d) => d,
+                Err(rusqlite::Error::QueryReturnedNoRows) => return Err(not_found(uri)),
+                Err(e) => return Err(RpcError::db(e)),
+            };
+            ("application/json", serde_json::to_string_pretty(&detail).unwrap_or_default())
+        }
+        ResourceUri::Session(sid) => {
+            let entries = db.session_transcript(sid).map_err(RpcError::db)?;
+            if entries.is_empty() {
+                return Err(not_found(uri));
+            }
+            ("text/markdown", format_transcript(sid, &entries))
+        }
+    };
+    Ok(json!({
+        "contents": [{ "uri": uri, "mimeType": mime, "text": text }]
+    }))
+}
+
+fn format_transcript(session_id: &str, entries: &[ConversationEntry]) -> String {
+    let mut out = format!("# Session {}\n", session_id);
+    if let Some(project) = entries.iter().find_map(|e| e.project.as_deref()) {
+        out.push_str(&format!("\nProject: {}\n", project));
+    }
+    for e in entries {
+        let ts = e.message_timestamp.as_deref().unwrap_or(&e.created_at);
+        out.push_str(&format!("\n## {} ({}", e.role, ts));
+        if let Some(et) = &e.entry_type {
+            out.push_str(&format!(", {}", et));
+        }
+        out.push_str(")\n\n");
+        out.push_str(&e.content);
+        out.push('\n');
+    }
+    out
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        let cut: String = s.chars().take(max).collect();
+        format!("{}...", cut)
+    }
+}
+
+/// A cheap value that changes whenever the resource does; `None` if it
+/// no longer exists.
+fn fingerprint(db: &Database, uri: &ResourceUri) -> rusqlite::Result<Option<String>> {
+    match uri {
+        ResourceUri::Memory { category, key } => Ok(db.get_memory(category, key)?
+            .map(|m| serde_json::to_string(&m).unwrap_or_default())),
+        ResourceUri::Task(id) => match mcp::task_detail(db, *id) {
+            Ok(d) => Ok(Some(d.to_string())),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        },
+        ResourceUri::Session(sid) => Ok(db.list_sessions(Some(sid))?
+            .into_iter()
+            .next()
+            .map(|s| format!("{}:{}", s.entries, s.last_entry_id))),
+    }
+}
+
+/// Resources a client has subscribed to, with the last fingerprint seen.
+#[derive(Default)]
+pub struct Subscriptions {
+    inner: Mutex<HashMap<String, (ResourceUri, Option<String>)>>,
+}
+
+impl Subscriptions {
+    pub fn subscribe(&self, db: &Database, uri: &str) -> Result<(), RpcError> {
+        let parsed: ResourceUri = uri.parse().map_err(RpcError::invalid_params)?;
+        let fp = fingerprint(db, &parsed).map_err(RpcError::db)?;
+        self.inner.lock().unwrap().insert(uri.to_string(), (parsed, fp));
+        Ok(())
+    }
+
+    pub fn unsubscribe(&self, uri: &str) -> bool {
+        self.inner.lock().unwrap().remove(uri).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().unwrap().is_empty()
+    }
+
+    /// Re-check every subscription and return the URIs that changed since
+    /// the last poll.
+    pub fn poll(&self, db: &Database) -> Vec<String> {
+        let mut inner = self.inner.lock().unwrap();
+        let mut changed = Vec::new();
+        for (uri, (parsed, last)) in inner.iter_mut() {
+            match fingerprint(db, parsed) {
+                Ok(fp) if fp != *last => {
+                    *last = fp;
+                    changed.push(uri.clone());
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("leafhill-persistent-memory: subscription check failed for {}: {}", uri, e),
+            }
+        }
+        changed
+    }
+}
+
+pub fn updated_notification(uri: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/resources/updated",
+        "params": { "uri": uri }
+    })
+}
+
+/// Poll subscriptions on a background thread with its own connection so
+/// changes written by other processes are noticed. `notify` receives each
+/// `notifications/resources/updated` message.
+pub fn spawn_poller<F>(db_path: &Path, subscriptions: Arc<Subscriptions>, notify: F)
+where
+    F: Fn(Value) + Send + 'static,
+{
+    let db_path = db_path.to_path_buf();
+    std::thread::spawn(move || {
+        let db = match Database::open(&db_path) {
+            Ok(db) => db,
+            Err(e) => {
+                eprintln!("leafhill-persistent-memory: subscription poller disabled: {}", e);
+                return;
+            }
+        };
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            if subscriptions.is_empty() {
+                continue;
+            }
+            for uri in subscriptions.poll(&db) {
+                notify(updated_notification(&uri));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uri_round_trip() {
+        let uris = vec![
+            ResourceUri::Memory { category: "facts".into(), key: "db/path with space".into() },
+            ResourceUri::Task(42),
+            ResourceUri::Session("abc-123-myproj".into()),
+        ];
+        for uri in uris {
+            let s = uri.to_string();
+            assert_eq!(s.parse::<ResourceUri>().unwrap(), uri, "{}", s);
+        }
+        assert_eq!(
+            ResourceUri::Memory { category: "a b".into(), key: "c/d".into() }.to_string(),
+            "memory://a%20b/c%2Fd"
+        );
+    }
+
+    #[test]
+    fn test_uri_invalid() {
+        assert!("memory://onlycategory".parse::<ResourceUri>().is_err());
+        assert!("task://abc".parse::<ResourceUri>().is_err());
+        assert!("session://".parse::<ResourceUri>().is_err());
+        assert!("file:///etc/passwd".parse::<ResourceUri>().unwrap_err().contains("Unknown resource scheme"));
+        assert!("memory://a/%zz".parse::<ResourceUri>().is_err());
+    }
+
+    #[test]
+    fn test_list_and_read() {
+        let db = Database::open_in_memory().unwrap();
+        db.store_memory("facts", "lang", "Rust", None, None).unwrap();
+        let task = db.create_task("proj", "Write docs", None, None, None, None, None, None, None, None, None, None).unwrap();
+        db.log_conversation("s1", "user", "hello", Some("proj"), None, None).unwrap();
+        db.log_conversation("s1", "assistant", "hi there", Some("proj"), None, None).unwrap();
+
+        let list = list(&db, None).unwrap();
+        let uris: Vec<&str> = list["resources"].as_array().unwrap().iter()
+            .map(|r| r["uri"].as_str().unwrap())
+            .collect();
+        assert!(uris.contains(&"memory://facts/lang"));
+        assert!(uris.contains(&format!("task://{}", task.id).as_str()));
+        assert!(uris.contains(&"session://s1"));
+        assert!(list.get("nextCursor").is_none());
+
+        let mem = read(&db, "memory://facts/lang").unwrap();
+        assert!(mem["contents"][0]["text"].as_str().unwrap().contains("Rust"));
+
+        let t = read(&db, &format!("task://{}", task.id)).unwrap();
+        assert!(t["contents"][0]["text"].as_str().unwrap().contains("Write docs"));
+
+        let s = read(&db, "session://s1").unwrap();
+        let text = s["contents"][0]["text"].as_str().unwrap();
+        assert_eq!(s["contents"][0]["mimeType"], "text/markdown");
+        assert!(text.find("hello").unwrap() < text.find("hi there").unwrap());
+
+        assert_eq!(read(&db, "memory://facts/missing").unwrap_err().code, RESOURCE_NOT_FOUND);
+        assert_eq!(read(&db, "task://9999").unwrap_err().code, RESOURCE_NOT_FOUND);
+        assert_eq!(read(&db, "bogus").unwrap_err().code, mcp::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_list_pagination() {
+        let db = Database::open_in_memory().unwrap();
+        for i in 0..(PAGE_SIZE + 5) {
+            db.store_memory("bulk", &format!("k{}", i), "v", None, None).unwrap();
+        }
+        let first = list(&db, None).unwrap();
+        assert_eq!(first["resources"].as_array().unwrap().len(), PAGE_SIZE);
+        let cursor = first["nextCursor"].as_str().unwrap();
+        let second = list(&db, Some(cursor)).unwrap();
+        assert_eq!(second["resources"].as_array().unwrap().len(), 5);
+        assert!(second.get("nextCursor").is_none());
+        assert_eq!(list(&db, Some("x")).unwrap_err().code, mcp::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_subscriptions_detect_changes() {
+        let db = Database::open_in_memory().unwrap();
+        db.store_memory("facts", "lang", "Rust", None, None).unwrap();
+        let subs = Subscriptions::default();
+        subs.subscribe(&db, "memory://facts/lang").unwrap();
+        subs.subscribe(&db, "session://s1").unwrap();
+        assert!(subs.poll(&db).is_empty());
+
+        db.store_memory("facts", "lang", "Rust 2021", None, None).unwrap();
+        assert_eq!(subs.poll(&db), vec!["memory://facts/lang".to_string()]);
+        assert!(subs.poll(&db).is_empty());
+
+        db.log_conversation("s1", "user", "new entry", None, None, None).unwrap();
+        assert_eq!(subs.poll(&db), vec!["session://s1".to_string()]);
+
+        db.delete_memory("facts", "lang").unwrap();
+        assert_eq!(subs.poll(&db), vec!["memory://facts/lang".to_string()]);
+
+        assert!(subs.unsubscribe("memory://facts/lang"));
+        assert!(!subs.unsubscribe("memory://facts/lang"));
+    }
+}
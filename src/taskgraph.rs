@@ -0,0 +1,148 @@
+use crate::db::Task;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Statuses that no longer hold up dependent tasks.
+pub const CLOSED_STATUSES: &[&str] = &["completed", "deleted"];
+
+/// Dependency analysis of a project's open tasks.
+#[derive(Debug, Serialize)]
+pub struct TaskPlan {
+    pub project: String,
+    /// Every open task, blockers before the tasks they block.
+    pub order: Vec<Task>,
+    /// Open tasks not yet started whose blockers are all closed.
+    pub ready: Vec<Task>,
+    /// Longest chain of open tasks, first blocker first.
+    pub critical_path: Vec<Task>,
+}
+
+/// Order `tasks` so every blocker precedes what it blocks, breaking ties by
+/// priority then ID. `deps` are `(blocker_id, blocked_id)` edges; edges to
+/// tasks outside `tasks` are ignored. Errors with the IDs left over when
+/// the graph contains a cycle.
+pub fn topological_order(tasks: &[Task], deps: &[(i64, i64)]) -> Result<Vec<Task>, Vec<i64>> {
+    let by_id: HashMap<i64, &Task> = tasks.iter().map(|t| (t.id, t)).collect();
+    let edges: Vec<(i64, i64)> = deps.iter().copied()
+        .filter(|(a, b)| by_id.contains_key(a) && by_id.contains_key(b))
+        .collect();
+
+    let mut indegree: HashMap<i64, usize> = tasks.iter().map(|t| (t.id, 0)).collect();
+    for (_, blocked) in &edges {
+        *indegree.get_mut(blocked).unwrap() += 1;
+    }
+
+    let mut available: BTreeSet<(u8, i64)> = indegree.iter()
+        .filter(|(_, d)| **d == 0)
+        .map(|(id, _)| (priority_rank(by_id[id]), *id))
+        .collect();
+    let mut order = Vec::with_capacity(tasks.len());
+    while let Some(next) = available.pop_first() {
+        let id = next.1;
+        order.push(by_id[&id].clone());
+        for (_, blocked) in edges.iter().filter(|(blocker, _)| *blocker == id) {
+            let d = indegree.get_mut(blocked).unwrap();
+            *d -= 1;
+            if *d == 0 {
+                available.insert((priority_rank(by_id[blocked]), *blocked));
+            }
+        }
+    }
+
+    if order.len() < tasks.len() {
+        let placed: HashSet<i64> = order.iter().map(|t| t.id).collect();
+        let mut stuck: Vec<i64> = tasks.iter().map(|t| t.id).filter(|id| !placed.contains(id)).collect();
+        stuck.sort_unstable();
+        return Err(stuck);
+    }
+    Ok(order)
+}
+
+/// Longest chain (by task count) through an already topologically ordered list.
+pub fn longest_chain(order: &[Task], deps: &[(i64, i64)]) -> Vec<Task> {
+    let position: HashMap<i64, usize> = order.iter().enumerate().map(|(i, t)| (t.id, i)).collect();
+    let mut length = vec![1usize; order.len()];
+    let mut previous: Vec<Option<usize>> = vec![None; order.len()];
+    for (i, task) in order.iter().enumerate() {
+        for (blocker, _) in deps.iter().filter(|(_, blocked)| *blocked == task.id) {
+            if let Some(&j) = position.get(blocker) {
+                if length[j] + 1 > length[i] {
+                    length[i] = length[j] + 1;
+                    previous[i] = Some(j);
+                }
+            }
+        }
+    }
+
+    let Some(mut i) = (0..order.len()).max_by_key(|&i| (length[i], std::cmp::Reverse(i))) else {
+        return Vec::new();
+    };
+    let mut chain = vec![order[i].clone()];
+    while let Some(j) = previous[i] {
+        chain.push(order[j].clone());
+        i = j;
+    }
+    chain.reverse();
+    chain
+}
+
+fn priority_rank(task: &Task) -> u8 {
+    match task.priority.as_deref() {
+        Some("high") => 0,
+        Some("medium") | None => 1,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: i64, priority: Option<&str>) -> Task {
+        Task {
+            id,
+            project: "p".to_string(),
+            subject: format!("t{}", id),
+            description: None,
+            status: "pending".to_string(),
+            priority: priority.map(|p| p.to_string()),
+            task_type: None,
+            parent_id: None,
+            due_date: None,
+            created_by: None,
+            assignee: None,
+            owner: None,
+            session_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+            recurrence: None,
+        }
+    }
+
+    fn ids(tasks: &[Task]) -> Vec<i64> {
+        tasks.iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn test_topological_order_respects_deps_then_priority() {
+        let tasks = vec![task(1, None), task(2, Some("high")), task(3, Some("low")), task(4, None)];
+        // 3 blocks 1, 1 blocks 4
+        let order = topological_order(&tasks, &[(3, 1), (1, 4), (9, 2)]).unwrap();
+        assert_eq!(ids(&order), vec![2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn test_topological_order_reports_cycle() {
+        let tasks = vec![task(1, None), task(2, None), task(3, None)];
+        assert_eq!(topological_order(&tasks, &[(1, 2), (2, 1)]).unwrap_err(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_longest_chain() {
+        let tasks = vec![task(1, None), task(2, None), task(3, None), task(4, None), task(5, None)];
+        let deps = [(1, 2), (2, 4), (3, 4), (4, 5)];
+        let order = topological_order(&tasks, &deps).unwrap();
+        assert_eq!(ids(&longest_chain(&order, &deps)), vec![1, 2, 4, 5]);
+        assert!(longest_chain(&[], &deps).is_empty());
+    }
+}
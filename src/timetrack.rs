@@ -0,0 +1,157 @@
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// How `time_report` buckets time entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeGroup {
+    Project,
+    Assignee,
+    TaskType,
+    /// `claude` for entries recorded in a Claude session, `human` otherwise.
+    Source,
+}
+
+impl TimeGroup {
+    pub const ALL: &[&str] = &["project", "assignee", "task_type", "source"];
+
+    /// SQL expression for the group key over `task_time_entries e JOIN tasks t`.
+    pub fn sql_expr(&self) -> &'static str {
+        match self {
+            TimeGroup::Project => "t.project",
+            TimeGroup::Assignee => "t.assignee",
+            TimeGroup::TaskType => "t.task_type",
+            TimeGroup::Source => "CASE WHEN e.session_id IS NULL THEN 'human' ELSE 'claude' END",
+        }
+    }
+}
+
+impl FromStr for TimeGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "project" => Ok(TimeGroup::Project),
+            "assignee" => Ok(TimeGroup::Assignee),
+            "task_type" => Ok(TimeGroup::TaskType),
+            "source" => Ok(TimeGroup::Source),
+            _ => Err(format!("Invalid group '{}'. Must be one of: {}", s, Self::ALL.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for TimeGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TimeGroup::Project => "project",
+            TimeGroup::Assignee => "assignee",
+            TimeGroup::TaskType => "task_type",
+            TimeGroup::Source => "source",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Total tracked time for one group of a report. `group` is `None` when the
+/// tasks have no value for the grouping field (e.g. unassigned).
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TimeReportRow {
+    pub group: Option<String>,
+    pub entries: i64,
+    pub seconds: i64,
+}
+
+/// Longest time entry accepted, in seconds: a year.
+pub const MAX_DURATION_SECONDS: i64 = 366 * 86400;
+
+/// Parse a duration such as `45m`, `2h`, `1h30m` or `1d` (bare numbers are
+/// seconds) into seconds.
+pub fn parse_duration(s: &str) -> Result<i64, String> {
+    let s = s.trim();
+    let err = || format!("Invalid duration '{}'. Use units s, m, h or d (e.g. 45m, 1h30m)", s);
+    let too_long = || format!("Invalid duration '{}'. Must be at most 366d", s);
+    if s.is_empty() {
+        return Err(err());
+    }
+    if let Ok(n) = s.parse::<i64>() {
+        return match n {
+            n if n <= 0 => Err(err()),
+            n if n > MAX_DURATION_SECONDS => Err(too_long()),
+            n => Ok(n),
+        };
+    }
+
+    let mut total = 0i64;
+    let mut num = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+            continue;
+        }
+        let n: i64 = num.parse().map_err(|_| err())?;
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(err()),
+        };
+        total = n.checked_mul(unit).and_then(|secs| total.checked_add(secs)).ok_or_else(too_long)?;
+        num.clear();
+    }
+    if !num.is_empty() || total <= 0 {
+        return Err(err());
+    }
+    if total > MAX_DURATION_SECONDS {
+        return Err(too_long());
+    }
+    Ok(total)
+}
+
+/// Render seconds as e.g. `1h 30m`, `45m` or `20s`.
+pub fn format_duration(seconds: i64) -> String {
+    let (h, m, s) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    match (h, m) {
+        (0, 0) => format!("{}s", s),
+        (0, _) => format!("{}m", m),
+        (_, 0) => format!("{}h", h),
+        _ => format!("{}h {}m", h, m),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(90));
+        assert_eq!(parse_duration("45m"), Ok(2700));
+        assert_eq!(parse_duration("1h30m"), Ok(5400));
+        assert_eq!(parse_duration("1d2h"), Ok(93600));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("2w").is_err());
+        assert_eq!(parse_duration("366d"), Ok(MAX_DURATION_SECONDS));
+        assert!(parse_duration("367d").unwrap_err().contains("at most 366d"));
+        assert!(parse_duration("99999999999999999d").unwrap_err().contains("at most 366d"));
+        assert!(parse_duration("9223372036854775807").unwrap_err().contains("at most 366d"));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(20), "20s");
+        assert_eq!(format_duration(2700), "45m");
+        assert_eq!(format_duration(7200), "2h");
+        assert_eq!(format_duration(5430), "1h 30m");
+    }
+
+    #[test]
+    fn test_time_group() {
+        assert_eq!("task_type".parse::<TimeGroup>(), Ok(TimeGroup::TaskType));
+        assert_eq!(TimeGroup::Source.to_string(), "source");
+        assert!("owner".parse::<TimeGroup>().unwrap_err().contains("Must be one of"));
+    }
+}
@@ -0,0 +1,80 @@
+//! Queries over the tool calls captured from transcripts.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Tools that write the file named by their `file_path` (or `notebook_path`) input.
+pub const EDIT_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// SQL expression for the file an edit tool call wrote.
+pub const EDITED_PATH_SQL: &str =
+    "COALESCE(json_extract(input, '$.file_path'), json_extract(input, '$.notebook_path'))";
+
+/// Filters for `Database::list_tool_calls`.
+#[derive(Debug, Default)]
+pub struct ToolCallFilter {
+    pub session_id: Option<String>,
+    pub project: Option<String>,
+    pub tool_name: Option<String>,
+    /// Only calls whose result was flagged as an error.
+    pub errors_only: bool,
+    /// Normalized `YYYY-MM-DD HH:MM:SS` lower bound on when the call was made.
+    pub since: Option<String>,
+}
+
+/// A file written by edit tool calls.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct EditedFile {
+    pub path: String,
+    pub edits: i64,
+    /// Edits whose result was an error.
+    pub failed: i64,
+    pub last_edited_at: Option<String>,
+}
+
+/// How often one tool was called and failed.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ToolFailureStat {
+    pub tool_name: String,
+    /// Calls with a result; calls still awaiting one are not counted.
+    pub calls: i64,
+    pub failures: i64,
+    pub failure_rate: f64,
+}
+
+/// Input keys that best describe a call, in order of preference.
+const SUMMARY_KEYS: &[&str] = &["command", "file_path", "notebook_path", "pattern", "url", "query", "description", "prompt"];
+
+/// One-line description of a call's input for listings: its command, path,
+/// pattern or URL when it has one, else the JSON itself, cut to `max` chars.
+pub fn summarize_input(input: &str, max: usize) -> String {
+    let value: Option<Value> = serde_json::from_str(input).ok();
+    let summary = value.as_ref()
+        .and_then(|v| SUMMARY_KEYS.iter().find_map(|k| v.get(*k).and_then(|s| s.as_str())))
+        .unwrap_or(input);
+    truncate_chars(&summary.split_whitespace().collect::<Vec<_>>().join(" "), max)
+}
+
+/// `text` cut to at most `max` chars, ending in `...` when shortened.
+pub fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max.saturating_sub(3)).collect();
+    format!("{}...", cut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_input() {
+        assert_eq!(summarize_input(r#"{"command": "cargo test\n  --workspace", "description": "Run tests"}"#, 80),
+            "cargo test --workspace");
+        assert_eq!(summarize_input(r#"{"file_path": "/r/src/main.rs", "old_string": "a"}"#, 80), "/r/src/main.rs");
+        assert_eq!(summarize_input(r#"{"todos": []}"#, 80), r#"{"todos": []}"#);
+        assert_eq!(summarize_input(r#"{"pattern": "abcdefghij"}"#, 8), "abcde...");
+        assert_eq!(summarize_input("not json", 80), "not json");
+    }
+}
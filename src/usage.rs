@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A dimension `stats tokens` can aggregate conversation token usage by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UsageGroup {
+    Project,
+    Session,
+    Model,
+    /// Calendar day (UTC) of the message.
+    Day,
+    /// Monday (UTC) of the message's week.
+    Week,
+}
+
+/// Message time: the transcript timestamp when known, else when it was stored.
+pub const MESSAGE_TIME_SQL: &str = "COALESCE(message_timestamp, created_at)";
+
+impl UsageGroup {
+    pub const ALL: &[&str] = &["project", "session", "model", "day", "week"];
+
+    /// SQL expression for the group key over the `conversations` table.
+    pub fn sql_expr(&self) -> String {
+        match self {
+            UsageGroup::Project => "project".to_string(),
+            UsageGroup::Session => "session_id".to_string(),
+            UsageGroup::Model => "model".to_string(),
+            UsageGroup::Day => format!("date({})", MESSAGE_TIME_SQL),
+            UsageGroup::Week => format!("date({}, 'weekday 0', '-6 days')", MESSAGE_TIME_SQL),
+        }
+    }
+
+    /// Parse a comma-separated list such as `project,week`.
+    pub fn parse_list(s: &str) -> Result<Vec<UsageGroup>, String> {
+        let mut groups = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let g = part.parse::<UsageGroup>()?;
+            if !groups.contains(&g) {
+                groups.push(g);
+            }
+        }
+        Ok(groups)
+    }
+}
+
+impl FromStr for UsageGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "project" => Ok(UsageGroup::Project),
+            "session" => Ok(UsageGroup::Session),
+            "model" => Ok(UsageGroup::Model),
+            "day" => Ok(UsageGroup::Day),
+            "week" => Ok(UsageGroup::Week),
+            _ => Err(format!("Invalid group '{}'. Must be one of: {}", s, Self::ALL.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for UsageGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            UsageGroup::Project => "project",
+            UsageGroup::Session => "session",
+            UsageGroup::Model => "model",
+            UsageGroup::Day => "day",
+            UsageGroup::Week => "week",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Filters for `Database::token_usage`.
+#[derive(Debug, Default)]
+pub struct UsageFilter {
+    pub project: Option<String>,
+    pub session_id: Option<String>,
+    pub model: Option<String>,
+    /// Normalized `YYYY-MM-DD HH:MM:SS` lower bound on message time.
+    pub since: Option<String>,
+}
+
+/// Summed token counts for one group key and model, as read from the database.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageRecord {
+    pub keys: Vec<Option<String>>,
+    pub model: Option<String>,
+    pub messages: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+}
+
+/// USD per million tokens for one model.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+}
+
+/// Prices keyed by model name or model-name prefix, e.g.
+/// `{"claude-sonnet-4": {"input": 3, "output": 15, "cache_write": 3.75, "cache_read": 0.3}}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct PriceTable(pub HashMap<String, ModelPrice>);
+
+impl PriceTable {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid price table: {}", e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read price table {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    /// The table at `CLAUDE_MEMORY_PRICES`, if set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        match std::env::var("CLAUDE_MEMORY_PRICES") {
+            Ok(path) if !path.trim().is_empty() => Self::load(Path::new(path.trim())).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// The price for `model`: an exact entry, else the longest key that is a
+    /// prefix of the model name (so `claude-sonnet-4` covers dated releases).
+    pub fn price_for(&self, model: &str) -> Option<&ModelPrice> {
+        self.0.get(model).or_else(|| {
+            self.0.iter()
+                .filter(|(k, _)| model.starts_with(k.as_str()))
+                .max_by_key(|(k, _)| k.len())
+                .map(|(_, p)| p)
+        })
+    }
+
+    fn cost(&self, r: &UsageRecord) -> Option<f64> {
+        let p = self.price_for(r.model.as_deref()?)?;
+        Some((r.input_tokens as f64 * p.input
+            + r.output_tokens as f64 * p.output
+            + r.cache_creation_tokens as f64 * p.cache_write
+            + r.cache_read_tokens as f64 * p.cache_read) / 1_000_000.0)
+    }
+}
+
+/// Aggregated usage for one group (or the overall total).
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct UsageRow {
+    pub group: BTreeMap<String, Option<String>>,
+    pub messages: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+    /// Share of prompt tokens served from cache: cache reads over all input.
+    pub cache_hit_ratio: Option<f64>,
+    /// Estimated cost in USD; `None` without a price table.
+    pub cost_usd: Option<f64>,
+    /// Models seen in this group that have no price.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unpriced_models: Vec<String>,
+}
+
+impl UsageRow {
+    fn add(&mut self, r: &UsageRecord, prices: Option<&PriceTable>) {
+        self.messages += r.messages;
+        self.input_tokens += r.input_tokens;
+        self.output_tokens += r.output_tokens;
+        self.cache_creation_tokens += r.cache_creation_tokens;
+        self.cache_read_tokens += r.cache_read_tokens;
+        if let Some(prices) = prices {
+            match prices.cost(r) {
+                Some(c) => *self.cost_usd.get_or_insert(0.0) += c,
+                None => {
+                    let name = r.model.clone().unwrap_or_else(|| "(unknown)".to_string());
+                    if !self.unpriced_models.contains(&name) {
+                        self.unpriced_models.push(name);
+                    }
+                    self.cost_usd.get_or_insert(0.0);
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        let prompt = self.input_tokens + self.cache_creation_tokens + self.cache_read_tokens;
+        self.cache_hit_ratio = (prompt > 0).then(|| self.cache_read_tokens as f64 / prompt as f64);
+        self.unpriced_models.sort();
+    }
+}
+
+/// Token usage report: one row per group, biggest spenders (by cost when
+/// priced, else total tokens) first, plus the overall total.
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub group_by: Vec<String>,
+    pub rows: Vec<UsageRow>,
+    pub total: UsageRow,
+}
+
+pub fn build_report(groups: &[UsageGroup], records: &[UsageRecord], prices: Option<&PriceTable>) -> UsageReport {
+    let mut by_key: BTreeMap<Vec<Option<String>>, UsageRow> = BTreeMap::new();
+    let mut total = UsageRow::default();
+    for r in records {
+        let row = by_key.entry(r.keys.clone()).or_insert_with(|| UsageRow {
+            group: groups.iter().map(|g| g.to_string()).zip(r.keys.iter().cloned()).collect(),
+            ..UsageRow::default()
+        });
+        row.add(r, prices);
+        total.add(r, prices);
+    }
+
+    let mut rows: Vec<UsageRow> = by_key.into_values().collect();
+    for row in &mut rows {
+        row.finish();
+    }
+    total.finish();
+    let tokens = |r: &UsageRow| r.input_tokens + r.output_tokens + r.cache_creation_tokens + r.cache_read_tokens;
+    rows.sort_by(|a, b| {
+        b.cost_usd.unwrap_or(0.0).total_cmp(&a.cost_usd.unwrap_or(0.0))
+            .then(tokens(b).cmp(&tokens(a)))
+    });
+
+    UsageReport {
+        group_by: groups.iter().map(|g| g.to_string()).collect(),
+        rows,
+        total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(keys: &[&str], model: &str, input: i64, output: i64, cache_read: i64) -> UsageRecord {
+        UsageRecord {
+            keys: keys.iter().map(|k| Some(k.to_string())).collect(),
+            model: Some(model.to_string()),
+            messages: 1,
+            input_tokens: input,
+            output_tokens: output,
+            cache_creation_tokens: 0,
+            cache_read_tokens: cache_read,
+        }
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        assert_eq!(UsageGroup::parse_list("project, week,project").unwrap(), vec![UsageGroup::Project, UsageGroup::Week]);
+        assert!(UsageGroup::parse_list("repo").unwrap_err().contains("Invalid group 'repo'"));
+    }
+
+    #[test]
+    fn test_price_lookup_prefers_longest_prefix() {
+        let prices = PriceTable::from_json(
+            r#"{"claude": {"input": 1, "output": 2}, "claude-opus": {"input": 15, "output": 75}}"#
+        ).unwrap();
+        assert_eq!(prices.price_for("claude-opus-4-20250514").unwrap().input, 15.0);
+        assert_eq!(prices.price_for("claude-haiku").unwrap().input, 1.0);
+        assert!(prices.price_for("gpt-4").is_none());
+        assert!(PriceTable::from_json(r#"{"x": {"input": "cheap"}}"#).is_err());
+    }
+
+    #[test]
+    fn test_build_report() {
+        let prices = PriceTable::from_json(r#"{"claude-sonnet": {"input": 3, "output": 15, "cache_read": 0.3}}"#).unwrap();
+        let records = vec![
+            record(&["repo-a"], "claude-sonnet-4", 1_000_000, 100_000, 3_000_000),
+            record(&["repo-b"], "claude-sonnet-4", 10, 10, 0),
+            record(&["repo-b"], "mystery", 10, 10, 0),
+        ];
+        let report = build_report(&[UsageGroup::Project], &records, Some(&prices));
+        assert_eq!(report.rows[0].group["project"].as_deref(), Some("repo-a"));
+        // 1M * $3 + 100k * $15 + 3M * $0.30
+        assert!((report.rows[0].cost_usd.unwrap() - 5.4).abs() < 1e-9);
+        assert_eq!(report.rows[0].cache_hit_ratio, Some(0.75));
+        assert_eq!(report.rows[1].unpriced_models, vec!["mystery"]);
+        assert_eq!(report.total.messages, 3);
+        assert_eq!(report.total.unpriced_models, vec!["mystery"]);
+
+        let unpriced = build_report(&[UsageGroup::Project], &records, None);
+        assert!(unpriced.total.cost_usd.is_none());
+        assert!(build_report(&[], &[], None).total.cache_hit_ratio.is_none());
+    }
+}
@@ -131,3 +131,93 @@ fn test_archive_purge() {
 
     cleanup(&dir);
 }
+
+#[test]
+fn test_gc_archives_expired_memories() {
+    let (db, archive) = temp_paths("gc");
+    let dir = db.parent().unwrap().to_path_buf();
+
+    let out = binary()
+        .env("CLAUDE_MEMORY_DB", &db)
+        .args(["store", "facts", "stale", "old news", "--expires-at", "2000-01-01"])
+        .output()
+        .expect("failed to run store");
+    assert!(out.status.success(), "store failed: {}", String::from_utf8_lossy(&out.stderr));
+    let out = binary()
+        .env("CLAUDE_MEMORY_DB", &db)
+        .args(["store", "facts", "fresh", "still true", "--ttl", "7d"])
+        .output()
+        .expect("failed to run store");
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Expires:"));
+
+    // Expired memories are hidden unless asked for
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["list"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("(1 memories)"));
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["list", "--include-expired"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("(2 memories)"));
+
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["gc", "--dry-run"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("1 expired memories would be removed"));
+
+    let out = binary()
+        .env("CLAUDE_MEMORY_DB", &db)
+        .args(["gc", "--archive", archive.to_str().unwrap()])
+        .output()
+        .expect("failed to run gc");
+    assert!(out.status.success(), "gc failed: {}", String::from_utf8_lossy(&out.stderr));
+
+    let envelope: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&archive).unwrap()).unwrap();
+    assert_eq!(envelope["counts"]["memories"], 1);
+    assert_eq!(envelope["filters"]["expired"], true);
+    assert_eq!(envelope["data"]["memories"][0]["key"], "stale");
+
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["list", "--include-expired"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("(1 memories)"));
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["history", "facts", "stale"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("No revisions found"));
+
+    cleanup(&dir);
+}
+
+#[test]
+fn test_archive_tasks_with_comments() {
+    let (db, archive) = temp_paths("comments");
+    let dir = db.parent().unwrap().to_path_buf();
+
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["task", "create", "proj", "Ship it"]).output().unwrap();
+    assert!(out.status.success(), "task create failed: {}", String::from_utf8_lossy(&out.stderr));
+    let out = binary()
+        .env("CLAUDE_MEMORY_DB", &db)
+        .args(["task", "comment", "add", "1", "Which version?", "--author", "alice"])
+        .output()
+        .expect("failed to run task comment add");
+    assert!(out.status.success(), "comment add failed: {}", String::from_utf8_lossy(&out.stderr));
+    binary()
+        .env("CLAUDE_MEMORY_DB", &db)
+        .args(["task", "comment", "add", "1", "Version 9", "--reply-to", "1"])
+        .output()
+        .unwrap();
+
+    let out = binary()
+        .env("CLAUDE_MEMORY_DB", &db)
+        .args(["archive", "create", archive.to_str().unwrap(), "--entity-type", "tasks", "--purge"])
+        .output()
+        .expect("failed to run archive create");
+    assert!(out.status.success(), "archive create failed: {}", String::from_utf8_lossy(&out.stderr));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("task_comments: 2"));
+
+    let out = binary()
+        .env("CLAUDE_MEMORY_DB", &db)
+        .args(["archive", "restore", archive.to_str().unwrap()])
+        .output()
+        .expect("failed to run archive restore");
+    assert!(out.status.success(), "archive restore failed: {}", String::from_utf8_lossy(&out.stderr));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("task_comments: 2 restored"));
+
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["task", "comment", "list", "1"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("#1 alice"));
+    assert!(stdout.contains("  #2 anonymous"));
+
+    cleanup(&dir);
+}
@@ -25,3 +25,64 @@ fn test_cli_invalid_subcommand() {
     let output = binary().arg("nonexistent-command").output().expect("failed to run");
     assert!(!output.status.success());
 }
+
+#[test]
+fn test_cli_migrate_up_to_and_status() {
+    let dir = std::env::temp_dir().join(format!("leafhill-cli-migrate-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("test.db");
+
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["migrate", "up", "--to", "1"]).output().expect("failed to run");
+    assert!(out.status.success(), "migrate up failed: {}", String::from_utf8_lossy(&out.stderr));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Applied migrations: 1"));
+
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["migrate", "status"]).output().expect("failed to run");
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("Schema version: 1"));
+    assert!(stdout.contains("pending"));
+
+    // Downgrading is rejected
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["migrate", "up"]).output().expect("failed to run");
+    assert!(out.status.success());
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["migrate", "up", "--to", "1"]).output().expect("failed to run");
+    assert!(!out.status.success());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cli_hook_events_build_session_timeline() {
+    use std::io::Write;
+
+    let dir = std::env::temp_dir().join(format!("leafhill-cli-hooks-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("test.db");
+    let hook = |payload: &str| {
+        let mut child = binary().env("CLAUDE_MEMORY_DB", &db).arg("hook-handler")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn().expect("failed to run");
+        child.stdin.take().unwrap().write_all(payload.as_bytes()).unwrap();
+        assert!(child.wait_with_output().unwrap().status.success());
+    };
+    let base = r#""session_id": "s1", "cwd": "/home/u/repo""#;
+    hook(&format!(r#"{{{}, "hook_event_name": "SessionStart", "source": "startup"}}"#, base));
+    hook(&format!(r#"{{{}, "hook_event_name": "UserPromptSubmit", "prompt": "run the tests"}}"#, base));
+    hook(&format!(r#"{{{}, "hook_event_name": "PreToolUse", "tool_name": "Bash", "tool_use_id": "toolu_1", "tool_input": {{"command": "cargo test"}}}}"#, base));
+    hook(&format!(r#"{{{}, "hook_event_name": "PostToolUse", "tool_name": "Bash", "tool_use_id": "toolu_1", "tool_input": {{"command": "cargo test"}}, "tool_response": {{"stdout": "2 passed", "stderr": ""}}}}"#, base));
+    hook(&format!(r#"{{{}, "hook_event_name": "SubagentStop", "stop_hook_active": false, "last_assistant_message": "found 3 callers"}}"#, base));
+    hook(&format!(r#"{{{}, "hook_event_name": "Notification", "message": "Claude needs your permission"}}"#, base));
+    hook(&format!(r#"{{{}, "hook_event_name": "SessionEnd", "reason": "logout"}}"#, base));
+
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["log", "timeline", "s1-repo"]).output().expect("failed to run");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let types: Vec<&str> = stdout.lines().filter_map(|l| l.split_whitespace().nth(2)).collect();
+    assert_eq!(types, ["session_start", "raw_user", "tool_use", "tool_result", "subagent", "notification", "session_end"]);
+    assert!(stdout.contains("Bash: cargo test"));
+
+    let out = binary().env("CLAUDE_MEMORY_DB", &db).args(["tool-calls", "list", "--session", "s1-repo"]).output().expect("failed to run");
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Bash ok"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -110,11 +110,17 @@ fn test_mcp_invalid_enum_error() {
     let lines: Vec<&str> = stdout.lines().collect();
     assert!(lines.len() >= 2);
 
+    // Bad arguments are a JSON-RPC Invalid params error with structured data
     let resp: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
-    assert_eq!(resp["result"]["isError"], true);
-    let text = resp["result"]["content"][0]["text"].as_str().unwrap();
-    assert!(text.contains("Invalid priority"));
-    assert!(text.contains("super_urgent"));
+    assert_eq!(resp["error"]["code"], -32602);
+    let message = resp["error"]["message"].as_str().unwrap();
+    assert!(message.contains("Invalid priority"));
+    assert!(message.contains("super_urgent"));
+    let data = &resp["error"]["data"];
+    assert_eq!(data["kind"], "invalid_value");
+    assert_eq!(data["field"], "priority");
+    assert_eq!(data["value"], "super_urgent");
+    assert_eq!(data["allowed"], serde_json::json!(["low", "medium", "high"]));
 
     cleanup(&db);
 }
@@ -146,9 +152,306 @@ fn test_mcp_unknown_tool() {
     assert!(lines.len() >= 2);
 
     let resp: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
-    assert_eq!(resp["result"]["isError"], true);
+    assert_eq!(resp["error"]["code"], -32602);
+    assert!(resp["error"]["message"].as_str().unwrap().contains("Unknown tool"));
+    assert_eq!(resp["error"]["data"]["tool"], "nonexistent_tool");
+
+    cleanup(&db);
+}
+
+/// Send raw lines to the MCP server and return stdout
+fn mcp_raw(db: &std::path::Path, lines: &[&str]) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_leafhill-persistent-memory"))
+        .env("CLAUDE_MEMORY_DB", db)
+        .arg("serve")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start serve");
+    {
+        let stdin = child.stdin.as_mut().unwrap();
+        for line in lines {
+            writeln!(stdin, "{}", line).unwrap();
+        }
+    }
+    drop(child.stdin.take());
+    let output = child.wait_with_output().expect("failed to wait");
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_mcp_protocol_errors_and_batches() {
+    let db = temp_db("protocol");
+
+    let stdout = mcp_raw(&db, &[
+        // Malformed JSON gets a parse error instead of silence
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "#,
+        // Not a request object
+        r#"{"jsonrpc": "2.0", "id": 2}"#,
+        r#"42"#,
+        r#"[]"#,
+        // Batch: two requests and a notification
+        r#"[{"jsonrpc": "2.0", "id": 3, "method": "ping"},
+            {"jsonrpc": "2.0", "method": "notifications/initialized"},
+            {"jsonrpc": "2.0", "id": 4, "method": "tools/call",
+             "params": {"name": "store_memory", "arguments": {"category": "c"}}}]"#
+            .replace('\n', " ").as_str(),
+        // Batch of notifications only: no reply at all
+        r#"[{"jsonrpc": "2.0", "method": "notifications/initialized"}]"#,
+        r#"{"jsonrpc": "2.0", "id": 5, "method": "no/such/method"}"#,
+    ]);
+    let msgs: Vec<serde_json::Value> = stdout.lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(msgs.len(), 6, "{}", stdout);
+
+    assert_eq!(msgs[0]["error"]["code"], -32700);
+    assert_eq!(msgs[0]["id"], serde_json::Value::Null);
+    assert_eq!(msgs[1]["error"]["code"], -32600);
+    assert_eq!(msgs[1]["id"], 2);
+    assert_eq!(msgs[2]["error"]["code"], -32600);
+    assert_eq!(msgs[3]["error"]["code"], -32600);
+
+    let batch = msgs[4].as_array().expect("batch reply must be an array");
+    assert_eq!(batch.len(), 2);
+    assert_eq!(batch[0]["id"], 3);
+    assert!(batch[0]["result"].is_object());
+    assert_eq!(batch[1]["id"], 4);
+    assert_eq!(batch[1]["error"]["code"], -32602);
+    assert_eq!(batch[1]["error"]["data"]["kind"], "missing_argument");
+    assert_eq!(batch[1]["error"]["data"]["field"], "key");
+
+    assert_eq!(msgs[5]["error"]["code"], -32601);
+
+    cleanup(&db);
+}
+
+#[test]
+fn test_mcp_resources_and_subscriptions() {
+    let db = temp_db("resources");
+
+    let store = |id: i64, value: &str| serde_json::json!({
+        "jsonrpc": "2.0", "id": id, "method": "tools/call",
+        "params": {"name": "store_memory", "arguments": {
+            "category": "facts", "key": "db path", "value": value
+        }}
+    });
+    let requests = vec![
+        serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        store(2, "~/.claude/memory.db"),
+        serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "resources/templates/list", "params": {}}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "resources/list", "params": {}}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 5, "method": "resources/read",
+            "params": {"uri": "memory://facts/db%20path"}}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 6, "method": "resources/subscribe",
+            "params": {"uri": "memory://facts/db%20path"}}),
+        store(7, "/var/lib/memory.db"),
+        serde_json::json!({"jsonrpc": "2.0", "id": 8, "method": "resources/read",
+            "params": {"uri": "task://999"}}),
+    ];
+
+    let stdout = mcp_request(&db, &requests);
+    let msgs: Vec<serde_json::Value> = stdout.lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let by_id = |id: i64| msgs.iter().find(|m| m["id"] == id).unwrap();
+
+    assert_eq!(by_id(1)["result"]["capabilities"]["resources"]["subscribe"], true);
+    assert_eq!(by_id(3)["result"]["resourceTemplates"].as_array().unwrap().len(), 3);
+    let listed = by_id(4)["result"]["resources"].as_array().unwrap();
+    assert!(listed.iter().any(|r| r["uri"] == "memory://facts/db%20path"));
+    let text = by_id(5)["result"]["contents"][0]["text"].as_str().unwrap();
+    assert!(text.contains("~/.claude/memory.db"));
+    assert_eq!(by_id(8)["error"]["code"], -32002);
+
+    // The update is announced right after the tool call that made it
+    let note_pos = msgs.iter()
+        .position(|m| m["method"] == "notifications/resources/updated")
+        .expect("expected a resources/updated notification");
+    assert_eq!(msgs[note_pos]["params"]["uri"], "memory://facts/db%20path");
+    let store_pos = msgs.iter().position(|m| m["id"] == 7).unwrap();
+    assert_eq!(note_pos, store_pos + 1);
+
+    cleanup(&db);
+}
+
+#[test]
+fn test_mcp_prompts() {
+    let db = temp_db("prompts");
+
+    let requests = vec![
+        serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call",
+            "params": {"name": "create_task", "arguments": {"project": "alpha", "subject": "Ship parser"}}}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "prompts/list", "params": {}}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "prompts/get",
+            "params": {"name": "recap_project", "arguments": {"project": "alpha"}}}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 5, "method": "prompts/get",
+            "params": {"name": "recap_project", "arguments": {}}}),
+    ];
+
+    let stdout = mcp_request(&db, &requests);
+    let msgs: Vec<serde_json::Value> = stdout.lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let by_id = |id: i64| msgs.iter().find(|m| m["id"] == id).unwrap();
+
+    assert!(by_id(1)["result"]["capabilities"]["prompts"].is_object());
+    assert_eq!(by_id(3)["result"]["prompts"].as_array().unwrap().len(), 3);
+    let text = by_id(4)["result"]["messages"][0]["content"]["text"].as_str().unwrap();
+    assert!(text.contains("Ship parser"));
+    assert_eq!(by_id(5)["error"]["code"], -32602);
+
+    cleanup(&db);
+}
+
+// ── Streamable HTTP transport ────────────────────────────────────────────
+
+struct HttpServer {
+    child: std::process::Child,
+    addr: String,
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn start_http_server(db: &std::path::Path) -> HttpServer {
+    use std::io::BufRead;
+    let mut child = Command::new(env!("CARGO_BIN_EXE_leafhill-persistent-memory"))
+        .env("CLAUDE_MEMORY_DB", db)
+        .args(["serve", "--http", "127.0.0.1:0"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start serve --http");
+    let stderr = child.stderr.take().unwrap();
+    let mut lines = std::io::BufReader::new(stderr).lines();
+    let addr = loop {
+        let line = lines.next().expect("server exited").unwrap();
+        if let Some(rest) = line.split("http://").nth(1) {
+            break rest.split('/').next().unwrap().to_string();
+        }
+    };
+    // Keep draining stderr so server logging never hits a closed pipe
+    std::thread::spawn(move || for _ in lines {});
+    HttpServer { child, addr }
+}
+
+/// Send one HTTP request and return (status, headers, body).
+fn http(
+    addr: &str,
+    method: &str,
+    session: Option<&str>,
+    body: Option<&serde_json::Value>,
+) -> (u16, Vec<(String, String)>, String) {
+    use std::io::Read;
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    let body = body.map(|b| serde_json::to_string(b).unwrap()).unwrap_or_default();
+    let mut req = format!(
+        "{} /mcp HTTP/1.1\r\nHost: {}\r\nAccept: application/json, text/event-stream\r\n\
+         Content-Type: application/json\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method, addr, body.len()
+    );
+    if let Some(s) = session {
+        req.push_str(&format!("Mcp-Session-Id: {}\r\n", s));
+    }
+    req.push_str("\r\n");
+    req.push_str(&body);
+    stream.write_all(req.as_bytes()).unwrap();
+
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    let (head, body) = raw.split_once("\r\n\r\n").unwrap();
+    let mut head_lines = head.lines();
+    let status = head_lines.next().unwrap().split(' ').nth(1).unwrap().parse().unwrap();
+    let headers = head_lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+    (status, headers, body.to_string())
+}
+
+fn initialize_session(addr: &str) -> String {
+    let (status, headers, body) = http(addr, "POST", None, Some(&serde_json::json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}
+    })));
+    assert_eq!(status, 200);
+    assert!(body.contains("leafhill-persistent-memory"));
+    headers.into_iter()
+        .find(|(k, _)| k == "mcp-session-id")
+        .map(|(_, v)| v)
+        .expect("initialize must return Mcp-Session-Id")
+}
+
+#[test]
+fn test_mcp_http_transport_sessions() {
+    let db = temp_db("http");
+    let server = start_http_server(&db);
+    let addr = server.addr.clone();
+
+    let s1 = initialize_session(&addr);
+    let s2 = initialize_session(&addr);
+    assert_ne!(s1, s2);
+
+    // Notifications are acknowledged with 202 and no body
+    let (status, _, _) = http(&addr, "POST", Some(&s1), Some(&serde_json::json!({
+        "jsonrpc": "2.0", "method": "notifications/initialized"
+    })));
+    assert_eq!(status, 202);
+
+    // Sessions write concurrently against the same database
+    let writers: Vec<_> = [s1.clone(), s2.clone()].into_iter().enumerate().map(|(i, sid)| {
+        let addr = addr.clone();
+        std::thread::spawn(move || {
+            for j in 0..5 {
+                let (status, _, body) = http(&addr, "POST", Some(&sid), Some(&serde_json::json!({
+                    "jsonrpc": "2.0", "id": 10 + j, "method": "tools/call",
+                    "params": {"name": "store_memory", "arguments": {
+                        "category": "http", "key": format!("s{}-k{}", i, j), "value": "concurrent write"
+                    }}
+                })));
+                assert_eq!(status, 200);
+                assert!(!body.contains("\"isError\":true"), "{}", body);
+            }
+        })
+    }).collect();
+    for w in writers {
+        w.join().unwrap();
+    }
+
+    let (status, _, body) = http(&addr, "POST", Some(&s2), Some(&serde_json::json!({
+        "jsonrpc": "2.0", "id": 20, "method": "tools/call",
+        "params": {"name": "list_memories", "arguments": {"category": "http"}}
+    })));
+    assert_eq!(status, 200);
+    let resp: serde_json::Value = serde_json::from_str(&body).unwrap();
     let text = resp["result"]["content"][0]["text"].as_str().unwrap();
-    assert!(text.contains("Unknown tool"));
+    assert!(text.contains("s0-k4") && text.contains("s1-k4"), "{}", text);
+
+    // Requests without a session are rejected, unknown sessions are 404
+    let ping = serde_json::json!({"jsonrpc": "2.0", "id": 30, "method": "ping"});
+    assert_eq!(http(&addr, "POST", None, Some(&ping)).0, 400);
+    assert_eq!(http(&addr, "POST", Some("nope"), Some(&ping)).0, 404);
+
+    // DELETE ends the session
+    assert_eq!(http(&addr, "DELETE", Some(&s1), None).0, 200);
+    assert_eq!(http(&addr, "POST", Some(&s1), Some(&ping)).0, 404);
+    assert_eq!(http(&addr, "POST", Some(&s2), Some(&ping)).0, 200);
+
+    // Batches work over HTTP too
+    let (status, _, body) = http(&addr, "POST", Some(&s2), Some(&serde_json::json!([
+        {"jsonrpc": "2.0", "id": 31, "method": "ping"},
+        {"jsonrpc": "2.0", "id": 32, "method": "ping"}
+    ])));
+    assert_eq!(status, 200);
+    let batch: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(batch.as_array().unwrap().len(), 2);
 
+    drop(server);
     cleanup(&db);
 }